@@ -1,18 +1,267 @@
+use crate::logging::LogLevel;
 use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
 pub enum MidiEventType {
     PitchBend,
     NoteOn,
     ControlChange,
+    /// HUI zone/port switch, `data_number` is `zone * 8 + port`
+    HuiSwitch,
+    /// 14-bit CC pair, `data_number` is the MSB controller (0-31), LSB is `data_number + 32`
+    ControlChange14,
+    /// NRPN with 14-bit data entry, the parameter lives in `nrpn_parameter`
+    Nrpn,
+}
+
+/// Control surface family, selects protocol details (display, touch notes)
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DeviceProfile {
+    #[default]
+    IconPlatformM,
+    MackieControl,
+    Hui,
+}
+
+impl DeviceProfile {
+    pub const ALL: [DeviceProfile; 3] = [
+        DeviceProfile::IconPlatformM,
+        DeviceProfile::MackieControl,
+        DeviceProfile::Hui,
+    ];
+}
+
+impl std::fmt::Display for DeviceProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeviceProfile::IconPlatformM => write!(f, "iCon Platform M+"),
+            DeviceProfile::MackieControl => write!(f, "Mackie Control (MCU)"),
+            DeviceProfile::Hui => write!(f, "Mackie HUI"),
+        }
+    }
+}
+
+/// How OSC travels between the bridge and Eos
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OscTransport {
+    #[default]
+    Udp,
+    /// OSC 1.0 over TCP, each packet prefixed with its length (Eos port 3032)
+    TcpPacketLength,
+    /// OSC 1.1 over TCP, SLIP framed (Eos port 3037)
+    TcpSlip,
+}
+
+impl OscTransport {
+    pub const ALL: [OscTransport; 3] = [
+        OscTransport::Udp,
+        OscTransport::TcpPacketLength,
+        OscTransport::TcpSlip,
+    ];
+}
+
+impl std::fmt::Display for OscTransport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OscTransport::Udp => write!(f, "UDP"),
+            OscTransport::TcpPacketLength => write!(f, "TCP (OSC 1.0, port 3032)"),
+            OscTransport::TcpSlip => write!(f, "TCP SLIP (OSC 1.1, port 3037)"),
+        }
+    }
+}
+
+/// One Eos console the bridge can talk to
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct EosTarget {
+    pub ip: String,
+    pub port: u16,
+}
+
+impl EosTarget {
+    pub fn addr(&self) -> String {
+        format!("{}:{}", self.ip, self.port)
+    }
+}
+
+/// Another OSC receiver mappings can send to, e.g. QLab or a media server. Always UDP.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct OscDestination {
+    pub name: String,
+    pub ip: String,
+    pub port: u16,
+}
+
+impl OscDestination {
+    pub fn addr(&self) -> String {
+        format!("{}:{}", self.ip, self.port)
+    }
+}
+
+/// A surface button that selects a mapping layer
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct LayerKey {
+    /// What mappings put in their `layer`
+    pub name: String,
+    /// `NoteOn`, or `HuiSwitch` on HUI surfaces
+    #[serde(default = "default_layer_event_type")]
+    pub event_type: MidiEventType,
+    pub data_number: u8,
+    /// A press switches the layer on until the next press, instead of only while held
+    #[serde(default)]
+    pub latch: bool,
+    /// Lights the button while its layer is active
+    #[serde(default)]
+    pub led: bool,
+}
+
+/// How a motor fader follows Eos feedback
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct MotorSettings {
+    /// Most motor updates per second, 0 for no limit
+    pub max_rate_hz: f32,
+    /// Changes smaller than this (0.0-1.0) don't move the motor
+    pub deadband: f32,
+    /// 0 jumps straight to the Eos level, towards 1 glides there more slowly
+    pub smoothing: f32,
+}
+
+impl Default for MotorSettings {
+    fn default() -> Self {
+        MotorSettings {
+            max_rate_hz: 50.0,
+            deadband: 0.002,
+            smoothing: 0.0,
+        }
+    }
+}
+
+/// One outgoing OSC argument. Plain values are sent as written; strings are templates,
+/// sent as floats where they read as numbers
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum OscArg {
+    Bool(bool),
+    Int(i32),
+    Float(f32),
+    Template(String),
+    /// `{ type = "int", value = "{note-35}" }`: a template converted to the given type
+    Typed {
+        #[serde(rename = "type")]
+        kind: OscArgType,
+        value: String,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OscArgType {
+    Float,
+    Int,
+    String,
+    Bool,
+}
+
+/// Eos command line sent through `/eos/newcmd`, or `/eos/cmd` to add to the current line
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct EosCommand {
+    /// Template, e.g. `Chan {note-35} Full`
+    pub text: String,
+    /// Ends the line with `#` so Eos runs it
+    #[serde(default)]
+    pub enter: bool,
+    /// Adds to whatever is on the command line instead of clearing it first
+    #[serde(default)]
+    pub append: bool,
+}
+
+impl EosCommand {
+    pub fn address(&self) -> &'static str {
+        if self.append {
+            "/eos/cmd"
+        } else {
+            "/eos/newcmd"
+        }
+    }
+}
+
+/// One message of an action sequence
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ActionStep {
+    /// Wait before this step, counted from the previous one
+    #[serde(default)]
+    pub delay_ms: u64,
+    /// Template, like a mapping's
+    #[serde(default)]
+    pub osc_address: String,
+    #[serde(default)]
+    pub osc_args: Vec<OscArg>,
+    /// Types this on the Eos command line instead of sending `osc_address`
+    #[serde(default)]
+    pub command: Option<EosCommand>,
+    /// Overrides the mapping's destination for this step
+    #[serde(default)]
+    pub destination: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct MidiOscMapping {
     pub event_type: MidiEventType,
+    /// MIDI channel 0-15 the control is on, any channel when unset. 14-bit and NRPN
+    /// feedback goes back there (channel 0 when unset)
+    #[serde(default)]
+    pub channel: Option<u8>,
     pub data_number: u8,
+    /// Last number of a range starting at `data_number`, so one templated mapping covers a bank
+    #[serde(default)]
+    pub data_number_end: Option<u8>,
+    /// May be a template (`/eos/chan/{note-35}`) filled in from the MIDI event, or an OSC
+    /// pattern for feedback only; the first placeholder or `{capture}` then picks the fader.
+    /// Unused by command mappings
+    #[serde(default)]
     pub osc_address: String,
     pub fixed_osc_value: Option<f32>,
+    /// Sent instead of the value, e.g. `[1, "{value}", { type = "int", value = "{note}" }]`
+    #[serde(default)]
+    pub osc_args: Vec<OscArg>,
+    #[serde(default)]
+    pub nrpn_parameter: Option<u16>,
+    /// Soft takeover: hold back values until the physical control reaches the Eos level
+    #[serde(default)]
+    pub pickup: bool,
+    /// Overrides `Config::motor` for this fader
+    #[serde(default)]
+    pub motor: Option<MotorSettings>,
+    /// Types this on the Eos command line instead of sending `osc_address`
+    #[serde(default)]
+    pub command: Option<EosCommand>,
+    /// Sent in order, each after its delay, instead of `osc_address`
+    #[serde(default)]
+    pub steps: Vec<ActionStep>,
+    /// Name of one of `Config::destinations`; Eos when unset
+    #[serde(default)]
+    pub destination: Option<String>,
+    /// Name of one of `Config::layers`; fires only while that layer is active.
+    /// Mappings without a layer fire when the active layer has nothing for the control
+    #[serde(default)]
+    pub layer: Option<String>,
+}
+
+impl MidiOscMapping {
+    /// Numbers this mapping listens to on input (NRPN is keyed on its parameter number)
+    pub fn input_numbers(&self) -> Option<RangeInclusive<u16>> {
+        match self.event_type {
+            MidiEventType::Nrpn => self.nrpn_parameter.map(|p| p..=p),
+            _ => {
+                let first = self.data_number as u16;
+                let last = self
+                    .data_number_end
+                    .map_or(first, |end| (end as u16).max(first));
+                Some(first..=last)
+            }
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -20,7 +269,44 @@ pub struct Config {
     pub eos_ip: String,
     pub eos_port: u16,
     pub listen_port: u16,
+    #[serde(default)]
+    pub osc_transport: OscTransport,
+    /// Eos user the bridge works as; `None` leaves the OSC interface default
+    #[serde(default)]
+    pub eos_user: Option<u16>,
+    /// Also prefix outgoing commands with `/eos/user/<n>` instead of only selecting the user at connect
+    #[serde(default)]
+    pub prefix_user_commands: bool,
     pub fader_bank_size: u8,
+    #[serde(default)]
+    pub device_profile: DeviceProfile,
+    /// Backup consoles in failover order, tried after `eos_ip`/`eos_port`
+    #[serde(default)]
+    pub backup_targets: Vec<EosTarget>,
+    /// Non-Eos receivers, picked by name in `MidiOscMapping::destination`
+    #[serde(default)]
+    pub destinations: Vec<OscDestination>,
+    /// Shift keys, picked by name in `MidiOscMapping::layer`
+    #[serde(default)]
+    pub layers: Vec<LayerKey>,
+    /// Lowest level written to `bridge.log`; the log panel still shows everything
+    #[serde(default)]
+    pub log_file_level: LogLevel,
+    /// How close a pickup fader must get to the Eos level to take over (0.0-1.0)
+    #[serde(default = "default_pickup_tolerance")]
+    pub pickup_tolerance: f32,
+    /// Continuous values are coalesced per OSC address to at most this many per second, 0 for no limit
+    #[serde(default = "default_max_osc_rate_hz")]
+    pub max_osc_rate_hz: f32,
+    /// After a fader is moved or let go, Eos echoes of the values it sent are ignored this long
+    #[serde(default = "default_echo_grace_ms")]
+    pub echo_grace_ms: u64,
+    /// Where Eos sends fader names, `{n}` is the strip number
+    #[serde(default = "default_label_address")]
+    pub label_address: String,
+    /// Motor behaviour for every fader without its own `motor` settings
+    #[serde(default)]
+    pub motor: MotorSettings,
     pub mappings: Vec<MidiOscMapping>,
 }
 
@@ -33,8 +319,18 @@ impl Default for Config {
             mappings.push(MidiOscMapping {
                 event_type: MidiEventType::PitchBend,
                 data_number: i as u8,
+                data_number_end: None,
+                channel: None,
                 osc_address: format!("/eos/fader/1/{}", i),
                 fixed_osc_value: None,
+                osc_args: vec![],
+                nrpn_parameter: None,
+                pickup: false,
+                motor: None,
+                command: None,
+                steps: vec![],
+                destination: None,
+                layer: None,
             });
         }
 
@@ -42,34 +338,129 @@ impl Default for Config {
         mappings.push(MidiOscMapping {
             event_type: MidiEventType::NoteOn,
             data_number: 94, // Play Button
+            data_number_end: None,
+            channel: None,
             osc_address: "/eos/key/go".to_string(),
             fixed_osc_value: Some(1.0),
+            osc_args: vec![],
+            nrpn_parameter: None,
+            pickup: false,
+            motor: None,
+            command: None,
+            steps: vec![],
+            destination: None,
+            layer: None,
         });
 
         // Default: Page Navigation (iCon Bank Buttons)
         mappings.push(MidiOscMapping {
             event_type: MidiEventType::NoteOn,
             data_number: 46, // Bank Left
+            data_number_end: None,
+            channel: None,
             osc_address: "/eos/fader/1/page/-1".to_string(),
             fixed_osc_value: Some(1.0),
+            osc_args: vec![],
+            nrpn_parameter: None,
+            pickup: false,
+            motor: None,
+            command: None,
+            steps: vec![],
+            destination: None,
+            layer: None,
         });
         mappings.push(MidiOscMapping {
             event_type: MidiEventType::NoteOn,
             data_number: 47, // Bank Right
+            data_number_end: None,
+            channel: None,
             osc_address: "/eos/fader/1/page/+1".to_string(),
             fixed_osc_value: Some(1.0),
+            osc_args: vec![],
+            nrpn_parameter: None,
+            pickup: false,
+            motor: None,
+            command: None,
+            steps: vec![],
+            destination: None,
+            layer: None,
         });
 
         Config {
             eos_ip: "127.0.0.1".to_string(),
             eos_port: 8000,
             listen_port: 8001,
+            osc_transport: OscTransport::default(),
+            eos_user: None,
+            prefix_user_commands: false,
             fader_bank_size: 10,
+            device_profile: DeviceProfile::default(),
+            backup_targets: vec![],
+            destinations: vec![],
+            layers: vec![],
+            log_file_level: LogLevel::default(),
+            pickup_tolerance: default_pickup_tolerance(),
+            max_osc_rate_hz: default_max_osc_rate_hz(),
+            echo_grace_ms: default_echo_grace_ms(),
+            label_address: default_label_address(),
+            motor: MotorSettings::default(),
             mappings,
         }
     }
 }
 
+fn default_label_address() -> String {
+    "/eos/out/fader/*/{n}/name".to_string()
+}
+
+fn default_pickup_tolerance() -> f32 {
+    0.02
+}
+
+fn default_max_osc_rate_hz() -> f32 {
+    60.0
+}
+
+fn default_echo_grace_ms() -> u64 {
+    500
+}
+
+fn default_layer_event_type() -> MidiEventType {
+    MidiEventType::NoteOn
+}
+
+impl Config {
+    /// Primary console followed by the backups
+    pub fn eos_targets(&self) -> Vec<EosTarget> {
+        let mut targets = vec![EosTarget {
+            ip: self.eos_ip.clone(),
+            port: self.eos_port,
+        }];
+        targets.extend(self.backup_targets.iter().cloned());
+        targets
+    }
+
+    /// Index into `destinations`
+    pub fn destination_index(&self, name: &str) -> Option<usize> {
+        self.destinations.iter().position(|d| d.name == name)
+    }
+}
+
+/// Rewrites `/eos/...` to `/eos/user/<n>/...` when a user prefix is configured.
+/// Session-level messages (ping, user selection, subscriptions) are left untouched.
+pub fn user_address(cfg: &Config, addr: &str) -> String {
+    const UNPREFIXED: [&str; 4] = ["/eos/ping", "/eos/user", "/eos/subscribe", "/eos/reset"];
+    match (cfg.eos_user, cfg.prefix_user_commands) {
+        (Some(user), true) if !UNPREFIXED.iter().any(|p| addr.starts_with(p)) => {
+            match addr.strip_prefix("/eos/") {
+                Some(rest) => format!("/eos/user/{}/{}", user, rest),
+                None => addr.to_string(),
+            }
+        }
+        _ => addr.to_string(),
+    }
+}
+
 pub fn float_to_pitch_bend(value: f32) -> u16 {
     (value.clamp(0.0, 1.0) * 16383.0).round() as u16
 }
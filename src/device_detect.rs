@@ -0,0 +1,298 @@
+use crate::config::DeviceProfile;
+use crate::hui;
+use midir::{MidiInput, MidiInputConnection, MidiOutput};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Universal SysEx Identity Request, broadcast to all device IDs (0x7F)
+const IDENTITY_REQUEST: [u8; 6] = [0xF0, 0x7E, 0x7F, 0x06, 0x01, 0xF7];
+/// Mackie Control "Device Query" addressed to the main unit (0x14)
+const MCU_DEVICE_QUERY: [u8; 7] = [0xF0, 0x00, 0x00, 0x66, 0x14, 0x00, 0xF7];
+/// What a HUI surface answers to the host ping
+const HUI_PING_REPLY: [u8; 3] = [0x90, 0x00, 0x7F];
+/// How long we wait for replies after querying one output port
+const REPLY_TIMEOUT: Duration = Duration::from_millis(300);
+
+/// Manufacturer IDs we can put a name on
+const KNOWN_MANUFACTURERS: &[(&[u8], &str)] = &[(&[0x00, 0x00, 0x66], "Mackie")];
+
+/// Sysex replies tagged with the index of the input port they arrived on
+type ReplyLog = Arc<Mutex<Vec<(usize, Vec<u8>)>>>;
+
+/// Decoded Universal SysEx Identity Reply
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceIdentity {
+    pub manufacturer: Vec<u8>,
+    pub family: u16,
+    pub member: u16,
+    pub version: [u8; 4],
+}
+
+/// A controller that answered our queries, with the in/out ports it lives on
+#[derive(Debug, Clone)]
+pub struct DetectedDevice {
+    pub in_port: String,
+    pub out_port: String,
+    pub name: String,
+    pub identity: Option<DeviceIdentity>,
+    pub profile: Option<DeviceProfile>,
+}
+
+impl std::fmt::Display for DetectedDevice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)?;
+        if let Some(id) = &self.identity {
+            let [a, b, c, d] = id.version;
+            write!(
+                f,
+                " [family {} model {} fw {}.{}.{}.{}]",
+                id.family, id.member, a, b, c, d
+            )?;
+        }
+        match self.profile {
+            Some(p) => write!(f, " ({})", p),
+            None => write!(f, " (unknown profile)"),
+        }
+    }
+}
+
+/// Parses `F0 7E <dev> 06 02 <mfr> <family> <member> <version> F7`
+pub fn parse_identity_reply(msg: &[u8]) -> Option<DeviceIdentity> {
+    if msg.len() < 6 || msg[0] != 0xF0 || msg[1] != 0x7E || msg[3] != 0x06 || msg[4] != 0x02 {
+        return None;
+    }
+    // One-byte manufacturer ID, or 0x00 followed by a two-byte extension
+    let mfr_len = if msg[5] == 0x00 { 3 } else { 1 };
+    let body = &msg[5..];
+    if body.len() < mfr_len + 8 + 1 {
+        return None;
+    }
+    let rest = &body[mfr_len..];
+    Some(DeviceIdentity {
+        manufacturer: body[..mfr_len].to_vec(),
+        family: rest[0] as u16 | (rest[1] as u16) << 7,
+        member: rest[2] as u16 | (rest[3] as u16) << 7,
+        version: [rest[4], rest[5], rest[6], rest[7]],
+    })
+}
+
+/// True for an MCU "Host Connection Query" sent back in answer to the device query
+pub fn is_mcu_reply(msg: &[u8]) -> bool {
+    msg.len() >= 7
+        && msg[..4] == [0xF0, 0x00, 0x00, 0x66]
+        && (msg[4] == 0x14 || msg[4] == 0x15)
+        && msg[5] == 0x01
+}
+
+fn manufacturer_name(id: &[u8]) -> Option<&'static str> {
+    KNOWN_MANUFACTURERS
+        .iter()
+        .find(|(m, _)| *m == id)
+        .map(|(_, name)| *name)
+}
+
+/// Picks a profile from what the device told us, falling back to the port name
+fn guess_profile(
+    port_name: &str,
+    identity: Option<&DeviceIdentity>,
+    speaks_mcu: bool,
+    speaks_hui: bool,
+) -> Option<DeviceProfile> {
+    let lower = port_name.to_lowercase();
+    if lower.contains("platform m") || lower.contains("icon") {
+        return Some(DeviceProfile::IconPlatformM);
+    }
+    // HUI surfaces don't answer the identity request, only the host ping
+    if speaks_hui || lower.contains("hui") {
+        return Some(DeviceProfile::Hui);
+    }
+    let is_mackie = identity.is_some_and(|i| manufacturer_name(&i.manufacturer) == Some("Mackie"));
+    if speaks_mcu || is_mackie {
+        return Some(DeviceProfile::MackieControl);
+    }
+    None
+}
+
+/// Queries every MIDI output port and matches replies to the input port they arrive on.
+/// This blocks for roughly `REPLY_TIMEOUT` per output port.
+pub fn detect_controllers() -> Vec<DetectedDevice> {
+    let replies: ReplyLog = Arc::new(Mutex::new(vec![]));
+
+    // Listen on every input port at once; midir needs one client per connection
+    let probe = match MidiInput::new("Eos-Detect-Probe") {
+        Ok(p) => p,
+        Err(_) => return vec![],
+    };
+    let in_names: Vec<String> = probe
+        .ports()
+        .iter()
+        .map(|p| probe.port_name(p).unwrap_or_default())
+        .collect();
+    let mut listeners: Vec<MidiInputConnection<()>> = vec![];
+    for (idx, name) in in_names.iter().enumerate() {
+        let Ok(mut midi_in) = MidiInput::new("Eos-Detect-In") else {
+            continue;
+        };
+        midi_in.ignore(midir::Ignore::None);
+        let Some(port) = midi_in
+            .ports()
+            .into_iter()
+            .find(|p| midi_in.port_name(p).unwrap_or_default() == *name)
+        else {
+            continue;
+        };
+        let sink = replies.clone();
+        if let Ok(conn) = midi_in.connect(
+            &port,
+            "detect",
+            move |_, msg, _| {
+                if msg.first() == Some(&0xF0) || msg == HUI_PING_REPLY {
+                    if let Ok(mut r) = sink.lock() {
+                        r.push((idx, msg.to_vec()));
+                    }
+                }
+            },
+            (),
+        ) {
+            listeners.push(conn);
+        }
+    }
+
+    let Ok(midi_out) = MidiOutput::new("Eos-Detect-Out") else {
+        return vec![];
+    };
+    let out_names: Vec<String> = midi_out
+        .ports()
+        .iter()
+        .map(|p| midi_out.port_name(p).unwrap_or_default())
+        .collect();
+
+    let mut found = vec![];
+    for out_name in out_names {
+        let Ok(midi_out) = MidiOutput::new("Eos-Detect-Out") else {
+            continue;
+        };
+        let Some(port) = midi_out
+            .ports()
+            .into_iter()
+            .find(|p| midi_out.port_name(p).unwrap_or_default() == out_name)
+        else {
+            continue;
+        };
+        let Ok(mut conn) = midi_out.connect(&port, "detect") else {
+            continue;
+        };
+        if let Ok(mut r) = replies.lock() {
+            r.clear();
+        }
+        let _ = conn.send(&IDENTITY_REQUEST);
+        let _ = conn.send(&MCU_DEVICE_QUERY);
+        let _ = conn.send(&hui::PING);
+        std::thread::sleep(REPLY_TIMEOUT);
+        conn.close();
+
+        let got = replies.lock().map(|r| r.clone()).unwrap_or_default();
+        for (idx, name) in in_names.iter().enumerate() {
+            let msgs: Vec<&Vec<u8>> = got
+                .iter()
+                .filter(|(i, _)| *i == idx)
+                .map(|(_, m)| m)
+                .collect();
+            if msgs.is_empty() {
+                continue;
+            }
+            let identity = msgs.iter().find_map(|m| parse_identity_reply(m));
+            let speaks_mcu = msgs.iter().any(|m| is_mcu_reply(m));
+            let speaks_hui = msgs.iter().any(|m| **m == HUI_PING_REPLY);
+            if identity.is_none() && !speaks_mcu && !speaks_hui {
+                continue;
+            }
+            let name_str = match identity
+                .as_ref()
+                .and_then(|i| manufacturer_name(&i.manufacturer))
+            {
+                Some(mfr) => format!("{} on {}", mfr, name),
+                None => name.clone(),
+            };
+            found.push(DetectedDevice {
+                in_port: name.clone(),
+                out_port: out_name.clone(),
+                name: name_str,
+                profile: guess_profile(name, identity.as_ref(), speaks_mcu, speaks_hui),
+                identity,
+            });
+        }
+    }
+
+    for l in listeners {
+        l.close();
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identity_replies_with_short_and_long_manufacturer_ids() {
+        let short = [
+            0xF0, 0x7E, 0x00, 0x06, 0x02, 0x47, 0x01, 0x02, 0x03, 0x00, 1, 2, 3, 4, 0xF7,
+        ];
+        let id = parse_identity_reply(&short).unwrap();
+        assert_eq!(id.manufacturer, [0x47]);
+        assert_eq!((id.family, id.member), (0x101, 3));
+        assert_eq!(id.version, [1, 2, 3, 4]);
+
+        let long = [
+            0xF0, 0x7E, 0x7F, 0x06, 0x02, 0x00, 0x00, 0x66, 0x14, 0x00, 0x00, 0x00, 1, 0, 0, 0,
+            0xF7,
+        ];
+        let id = parse_identity_reply(&long).unwrap();
+        assert_eq!(manufacturer_name(&id.manufacturer), Some("Mackie"));
+        assert_eq!((id.family, id.member), (0x14, 0));
+    }
+
+    #[test]
+    fn truncated_or_foreign_replies_are_ignored() {
+        assert_eq!(parse_identity_reply(&[0xF0, 0x7E, 0x00, 0x06, 0x02]), None);
+        // Three byte ID with too little after it
+        let cut = [
+            0xF0, 0x7E, 0x00, 0x06, 0x02, 0x00, 0x00, 0x66, 0x14, 0x00, 0xF7,
+        ];
+        assert_eq!(parse_identity_reply(&cut), None);
+        // An identity request, not a reply
+        assert_eq!(parse_identity_reply(&IDENTITY_REQUEST), None);
+    }
+
+    #[test]
+    fn mcu_main_and_extender_replies() {
+        let reply = |model| [0xF0, 0x00, 0x00, 0x66, model, 0x01, 0x00, 0xF7];
+        assert!(is_mcu_reply(&reply(0x14)));
+        assert!(is_mcu_reply(&reply(0x15)));
+        assert!(!is_mcu_reply(&reply(0x05)));
+        assert!(!is_mcu_reply(&MCU_DEVICE_QUERY));
+        assert!(!is_mcu_reply(&[0xF0, 0x00, 0x00, 0x66, 0x14, 0x01]));
+    }
+
+    #[test]
+    fn profiles_from_replies_and_port_names() {
+        assert_eq!(
+            guess_profile("iCON Platform M+", None, true, false),
+            Some(DeviceProfile::IconPlatformM)
+        );
+        assert_eq!(
+            guess_profile("USB MIDI", None, false, true),
+            Some(DeviceProfile::Hui)
+        );
+        assert_eq!(
+            guess_profile("Control 24 HUI", None, false, false),
+            Some(DeviceProfile::Hui)
+        );
+        assert_eq!(
+            guess_profile("USB MIDI", None, true, false),
+            Some(DeviceProfile::MackieControl)
+        );
+        assert_eq!(guess_profile("USB MIDI", None, false, false), None);
+    }
+}
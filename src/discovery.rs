@@ -0,0 +1,134 @@
+//! Finds Eos family consoles and Nomad instances on the LAN with a broadcast `/eos/ping`
+use crate::osc_transport::for_each_message;
+use rosc::{decoder, encoder, OscMessage, OscPacket, OscType};
+use std::net::{Ipv4Addr, SocketAddr};
+use tokio::net::UdpSocket;
+use tokio::time::{timeout_at, Duration, Instant};
+
+/// How long we collect replies after broadcasting
+const DISCOVERY_WINDOW: Duration = Duration::from_millis(1500);
+const DISCOVERY_TAG: &str = "EosBridgeDiscovery";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiscoveredConsole {
+    pub ip: String,
+    /// Show the console has loaded, from `/eos/out/show/name`, so two desks can be told apart
+    pub name: Option<String>,
+    /// Software version reported by the console, when it answered `/eos/get/version`
+    pub version: Option<String>,
+}
+
+impl std::fmt::Display for DiscoveredConsole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Eos")?;
+        if let Some(v) = &self.version {
+            write!(f, " {}", v)?;
+        }
+        if let Some(name) = &self.name {
+            write!(f, " \"{}\"", name)?;
+        }
+        write!(f, " @ {}", self.ip)
+    }
+}
+
+fn encode(addr: &str, args: Vec<OscType>) -> Vec<u8> {
+    encoder::encode(&OscPacket::Message(OscMessage {
+        addr: addr.into(),
+        args,
+    }))
+    .unwrap_or_default()
+}
+
+/// Broadcasts a ping to `eos_port` and lists every console that answers.
+/// Eos replies either to the sender or to its configured OSC TX port, so we listen on both.
+pub async fn discover_consoles(eos_port: u16, listen_port: u16) -> Vec<DiscoveredConsole> {
+    let Ok(socket) = UdpSocket::bind("0.0.0.0:0").await else {
+        return vec![];
+    };
+    if socket.set_broadcast(true).is_err() {
+        return vec![];
+    }
+    // Fails while the bridge itself holds the port, the ephemeral socket still works then
+    let tx_port_socket = UdpSocket::bind(format!("0.0.0.0:{}", listen_port))
+        .await
+        .ok();
+
+    let target = SocketAddr::from((Ipv4Addr::BROADCAST, eos_port));
+    let ping = encode("/eos/ping", vec![OscType::String(DISCOVERY_TAG.into())]);
+    let version = encode("/eos/get/version", vec![]);
+    // Eos only reports its show name to subscribers. Each console that answers is
+    // subscribed for the discovery window, directly so no other desk is touched
+    let subscribe = |on| encode("/eos/subscribe", vec![OscType::Int(on)]);
+    let console = |ip: &str| ip.parse().map(|ip| SocketAddr::new(ip, eos_port)).ok();
+    let _ = socket.send_to(&ping, target).await;
+    let _ = socket.send_to(&version, target).await;
+
+    let mut found: Vec<DiscoveredConsole> = vec![];
+    let deadline = Instant::now() + DISCOVERY_WINDOW;
+    let mut buf = [0u8; 4096];
+    let mut buf2 = [0u8; 4096];
+    loop {
+        let recv = async {
+            match &tx_port_socket {
+                Some(s2) => tokio::select! {
+                    r = socket.recv_from(&mut buf) => r.map(|(n, a)| (buf[..n].to_vec(), a)),
+                    r = s2.recv_from(&mut buf2) => r.map(|(n, a)| (buf2[..n].to_vec(), a)),
+                },
+                None => socket
+                    .recv_from(&mut buf)
+                    .await
+                    .map(|(n, a)| (buf[..n].to_vec(), a)),
+            }
+        };
+        let Ok(res) = timeout_at(deadline, recv).await else {
+            break;
+        };
+        let Ok((data, from)) = res else {
+            continue;
+        };
+        let Ok((_, packet)) = decoder::decode_udp(&data) else {
+            continue;
+        };
+        // Replies can come in bundles
+        let mut msgs = vec![];
+        for_each_message(&packet, &mut |m| msgs.push(m.clone()));
+
+        let ip = from.ip().to_string();
+        let known = found.len();
+        for msg in msgs {
+            if !msg.addr.starts_with("/eos/out/") {
+                continue;
+            }
+            let idx = match found.iter().position(|c| c.ip == ip) {
+                Some(i) => i,
+                None => {
+                    found.push(DiscoveredConsole {
+                        ip: ip.clone(),
+                        name: None,
+                        version: None,
+                    });
+                    found.len() - 1
+                }
+            };
+            let Some(OscType::String(v)) = msg.args.first() else {
+                continue;
+            };
+            match msg.addr.as_str() {
+                "/eos/out/get/version" => found[idx].version = Some(v.clone()),
+                "/eos/out/show/name" if !v.is_empty() => found[idx].name = Some(v.clone()),
+                _ => {}
+            }
+        }
+        for c in &found[known..] {
+            if let Some(addr) = console(&c.ip) {
+                let _ = socket.send_to(&subscribe(1), addr).await;
+            }
+        }
+    }
+    for c in &found {
+        if let Some(addr) = console(&c.ip) {
+            let _ = socket.send_to(&subscribe(0), addr).await;
+        }
+    }
+    found
+}
@@ -0,0 +1,437 @@
+//! Per-fader state shared by the MIDI input handler and the Eos feedback loop
+use crate::config::MotorSettings;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Most recent sent values remembered per fader
+const ECHO_HISTORY: usize = 16;
+/// Eos hands levels back with some rounding
+const ECHO_TOLERANCE: f32 = 0.005;
+/// Feedback this soon after the fader moved is taken as Eos echoing our own value
+const PICKUP_ECHO_WINDOW: Duration = Duration::from_millis(500);
+
+pub type SharedFaders = Arc<Mutex<FaderState>>;
+
+#[derive(Debug, Default)]
+pub struct FaderState {
+    /// Touch sensors, indexed like the fader bank (9 is the master)
+    touched: [bool; 13],
+    /// Values we sent to Eos recently, indexed like `touched`
+    echoes: [EchoGuard; 13],
+    /// Soft takeover, keyed on the mapping's OSC address
+    pickup: HashMap<String, Pickup>,
+    /// Motor position and Eos target, indexed like `touched`
+    motors: [Motor; 13],
+    /// Recorded time during a session replay, wall clock otherwise
+    replay_clock: Option<Instant>,
+    /// Eos fader page as moved by our own `/page/` commands, 0 until the first one
+    page: u16,
+}
+
+/// Way to move a physical fader to pick up the Eos level
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PickupDirection {
+    Up,
+    Down,
+}
+
+#[derive(Debug, Default)]
+struct Pickup {
+    eos: Option<f32>,
+    physical: Option<f32>,
+    engaged: bool,
+    last_input: Option<Instant>,
+}
+
+impl Pickup {
+    fn direction(&self) -> Option<PickupDirection> {
+        match (self.engaged, self.physical, self.eos) {
+            (false, Some(p), Some(e)) if p < e => Some(PickupDirection::Up),
+            (false, Some(p), Some(e)) if p > e => Some(PickupDirection::Down),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+struct EchoGuard {
+    sent: VecDeque<f32>,
+    /// Last time the fader was moved or let go
+    last_active: Option<Instant>,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Motor {
+    /// Latest Eos level not yet reached
+    target: Option<f32>,
+    /// Where the motor was last sent
+    position: Option<f32>,
+    last_move: Option<Instant>,
+    settings: MotorSettings,
+}
+
+impl Motor {
+    /// Next position to send, if the rate limit and deadband allow one
+    fn step(&mut self, now: Instant) -> Option<f32> {
+        let target = self.target?;
+        let s = self.settings;
+        if s.max_rate_hz > 0.0 {
+            let interval = Duration::from_secs_f32(1.0 / s.max_rate_hz);
+            if self
+                .last_move
+                .is_some_and(|t| now.duration_since(t) < interval)
+            {
+                return None;
+            }
+        }
+        let next = match self.position {
+            // First level we hear about, nothing to smooth from
+            None => {
+                self.target = None;
+                target
+            }
+            Some(from) => {
+                let remaining = target - from;
+                if remaining.abs() <= s.deadband {
+                    self.target = None;
+                    return None;
+                }
+                let step = remaining * (1.0 - s.smoothing.clamp(0.0, 0.99));
+                if (remaining - step).abs() <= s.deadband {
+                    self.target = None;
+                    target
+                } else {
+                    // Heavy smoothing still has to make progress
+                    let next = from + step.signum() * step.abs().max(s.deadband);
+                    // With no deadband the step can round away to nothing next to the target
+                    if next == from {
+                        self.target = None;
+                        target
+                    } else {
+                        next
+                    }
+                }
+            }
+        };
+        self.position = Some(next);
+        self.last_move = Some(now);
+        Some(next)
+    }
+}
+
+impl FaderState {
+    pub fn shared() -> SharedFaders {
+        Arc::new(Mutex::new(FaderState::default()))
+    }
+
+    pub fn now(&self) -> Instant {
+        self.replay_clock.unwrap_or_else(Instant::now)
+    }
+
+    pub fn set_replay_clock(&mut self, now: Instant) {
+        self.replay_clock = Some(now);
+    }
+
+    /// Current fader page, counted from 1
+    pub fn page(&self) -> u16 {
+        self.page.max(1)
+    }
+
+    /// Follows a page command on its way to Eos: `.../page/+1`, `.../page/-1` or `.../page/3`
+    pub fn track_page(&mut self, addr: &str) {
+        let Some((path, step)) = addr.rsplit_once('/') else {
+            return;
+        };
+        if !path.ends_with("/page") {
+            return;
+        }
+        let Ok(n) = step.parse::<i32>() else {
+            return;
+        };
+        let page = if step.starts_with(['+', '-']) {
+            self.page() as i32 + n
+        } else {
+            n
+        };
+        self.page = page.clamp(1, u16::MAX as i32) as u16;
+    }
+
+    pub fn is_touched(&self, idx: u8) -> bool {
+        self.touched.get(idx as usize).copied().unwrap_or(false)
+    }
+
+    pub fn set_touched(&mut self, idx: u8, touched: bool, now: Instant) {
+        let Some(t) = self.touched.get_mut(idx as usize) else {
+            return;
+        };
+        if *t && !touched {
+            // Echoes of the last moves are still on their way back from Eos
+            self.echoes[idx as usize].last_active = Some(now);
+        }
+        *t = touched;
+    }
+
+    /// Remembers a level sent to Eos so its echo doesn't drive the motor
+    pub fn note_sent(&mut self, idx: u8, level: f32, now: Instant) {
+        let Some(guard) = self.echoes.get_mut(idx as usize) else {
+            return;
+        };
+        if guard.sent.len() == ECHO_HISTORY {
+            guard.sent.pop_front();
+        }
+        guard.sent.push_back(level);
+        guard.last_active = Some(now);
+    }
+
+    /// True if `level` is Eos repeating something we sent within `grace` of the
+    /// fader last being moved or released
+    pub fn is_echo(&mut self, idx: u8, level: f32, grace: Duration, now: Instant) -> bool {
+        let Some(guard) = self.echoes.get_mut(idx as usize) else {
+            return false;
+        };
+        if guard
+            .last_active
+            .is_none_or(|t| now.duration_since(t) >= grace)
+        {
+            guard.sent.clear();
+            return false;
+        }
+        guard
+            .sent
+            .iter()
+            .any(|v| (v - level).abs() <= ECHO_TOLERANCE)
+    }
+
+    /// New Eos level for a motor fader; returns the position to send right away, if any.
+    /// Whatever is held back by the rate limit or smoothing comes out of `motor_pending`
+    pub fn motor_feedback(
+        &mut self,
+        idx: u8,
+        level: f32,
+        settings: MotorSettings,
+        now: Instant,
+    ) -> Option<f32> {
+        let touched = self.is_touched(idx);
+        let motor = self.motors.get_mut(idx as usize)?;
+        if touched {
+            // The hand owns the fader; resync from scratch on the next level after release
+            *motor = Motor::default();
+            return None;
+        }
+        motor.target = Some(level);
+        motor.settings = settings;
+        motor.step(now)
+    }
+
+    /// Motor moves that became due since the last call
+    pub fn motor_pending(&mut self, now: Instant) -> Vec<(u8, f32)> {
+        let mut due = vec![];
+        for (idx, motor) in self.motors.iter_mut().enumerate() {
+            if self.touched[idx] {
+                continue;
+            }
+            if let Some(pos) = motor.step(now) {
+                due.push((idx as u8, pos));
+            }
+        }
+        due
+    }
+
+    /// True while a motor still has somewhere to go
+    pub fn motors_busy(&self) -> bool {
+        self.motors.iter().any(|m| m.target.is_some())
+    }
+
+    /// Records a physical move; returns true once the fader has picked up the Eos level
+    /// (within `tolerance`, or by crossing it) and the value may be sent
+    pub fn pickup_input(&mut self, addr: &str, value: f32, tolerance: f32, now: Instant) -> bool {
+        let p = self.pickup.entry(addr.to_string()).or_default();
+        let crossed = match (p.physical, p.eos) {
+            (Some(prev), Some(e)) => (prev < e) != (value < e),
+            _ => false,
+        };
+        p.physical = Some(value);
+        p.last_input = Some(now);
+        if !p.engaged {
+            p.engaged = crossed || p.eos.is_none_or(|e| (value - e).abs() <= tolerance);
+        }
+        p.engaged
+    }
+
+    /// Tracks the Eos level; a change we didn't cause (page change, cue, another desk)
+    /// releases the fader until it is picked up again
+    pub fn pickup_feedback(&mut self, addr: &str, level: f32, tolerance: f32, now: Instant) {
+        let p = self.pickup.entry(addr.to_string()).or_default();
+        p.eos = Some(level);
+        let echo = p
+            .last_input
+            .is_some_and(|t| now.duration_since(t) < PICKUP_ECHO_WINDOW);
+        if p.engaged && !echo && p.physical.is_some_and(|v| (v - level).abs() > tolerance) {
+            p.engaged = false;
+        }
+    }
+
+    /// Which way to move, or `None` when the fader is in control (or nothing is known yet)
+    pub fn pickup_direction(&self, addr: &str) -> Option<PickupDirection> {
+        self.pickup.get(addr).and_then(Pickup::direction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(t0: Instant, n: u64) -> Instant {
+        t0 + Duration::from_millis(n)
+    }
+
+    const FADER: &str = "/eos/fader/1/1";
+
+    #[test]
+    fn pickup_engages_near_the_eos_level() {
+        let t0 = Instant::now();
+        let mut faders = FaderState::default();
+        // Nothing heard from Eos yet, so nothing to pick up
+        assert!(faders.pickup_input(FADER, 0.2, 0.02, t0));
+        let mut faders = FaderState::default();
+        faders.pickup_feedback(FADER, 0.5, 0.02, t0);
+        assert!(!faders.pickup_input(FADER, 0.2, 0.02, t0));
+        assert_eq!(faders.pickup_direction(FADER), Some(PickupDirection::Up));
+        assert!(!faders.pickup_input(FADER, 0.45, 0.02, t0));
+        assert!(faders.pickup_input(FADER, 0.49, 0.02, t0));
+        assert_eq!(faders.pickup_direction(FADER), None);
+    }
+
+    #[test]
+    fn pickup_engages_when_crossing_the_eos_level() {
+        let t0 = Instant::now();
+        let mut faders = FaderState::default();
+        faders.pickup_feedback(FADER, 0.5, 0.02, t0);
+        assert!(!faders.pickup_input(FADER, 0.8, 0.02, t0));
+        assert_eq!(faders.pickup_direction(FADER), Some(PickupDirection::Down));
+        // A fast move jumps straight past the level
+        assert!(faders.pickup_input(FADER, 0.3, 0.02, t0));
+    }
+
+    #[test]
+    fn pickup_releases_when_eos_moves_on_its_own() {
+        let t0 = Instant::now();
+        let mut faders = FaderState::default();
+        assert!(faders.pickup_input(FADER, 0.5, 0.02, t0));
+        // Eos echoing an older value right after a move keeps the fader engaged
+        faders.pickup_feedback(FADER, 0.4, 0.02, ms(t0, 100));
+        assert!(faders.pickup_input(FADER, 0.5, 0.02, ms(t0, 200)));
+        // A cue or page change later on lets go of it
+        faders.pickup_feedback(FADER, 0.9, 0.02, ms(t0, 1000));
+        assert_eq!(faders.pickup_direction(FADER), Some(PickupDirection::Up));
+        assert!(!faders.pickup_input(FADER, 0.6, 0.02, ms(t0, 1100)));
+    }
+
+    #[test]
+    fn echoes_only_match_within_the_grace_window() {
+        let t0 = Instant::now();
+        let grace = Duration::from_millis(300);
+        let mut faders = FaderState::default();
+        faders.note_sent(1, 0.5, t0);
+        assert!(faders.is_echo(1, 0.502, grace, ms(t0, 100)));
+        assert!(!faders.is_echo(1, 0.6, grace, ms(t0, 100)));
+        // Other faders have their own history
+        assert!(!faders.is_echo(2, 0.5, grace, ms(t0, 100)));
+        assert!(!faders.is_echo(1, 0.5, grace, ms(t0, 300)));
+        // Once the window has passed the history is gone
+        faders.set_touched(1, true, ms(t0, 400));
+        faders.set_touched(1, false, ms(t0, 500));
+        assert!(!faders.is_echo(1, 0.5, grace, ms(t0, 600)));
+    }
+
+    #[test]
+    fn releasing_a_fader_reopens_the_window() {
+        let t0 = Instant::now();
+        let grace = Duration::from_millis(300);
+        let mut faders = FaderState::default();
+        faders.set_touched(1, true, t0);
+        faders.note_sent(1, 0.5, t0);
+        faders.set_touched(1, false, ms(t0, 1000));
+        assert!(faders.is_echo(1, 0.5, grace, ms(t0, 1200)));
+        assert!(!faders.is_echo(1, 0.5, grace, ms(t0, 1300)));
+    }
+
+    fn motor(max_rate_hz: f32, deadband: f32, smoothing: f32) -> MotorSettings {
+        MotorSettings {
+            max_rate_hz,
+            deadband,
+            smoothing,
+        }
+    }
+
+    #[test]
+    fn motor_rate_limit_keeps_the_latest_level() {
+        let t0 = Instant::now();
+        let s = motor(10.0, 0.0, 0.0);
+        let mut faders = FaderState::default();
+        assert_eq!(faders.motor_feedback(1, 0.2, s, t0), Some(0.2));
+        assert_eq!(faders.motor_feedback(1, 0.3, s, ms(t0, 20)), None);
+        assert_eq!(faders.motor_feedback(1, 0.4, s, ms(t0, 40)), None);
+        assert!(faders.motor_pending(ms(t0, 99)).is_empty());
+        assert_eq!(faders.motor_pending(ms(t0, 101)), [(1, 0.4)]);
+        assert!(!faders.motors_busy());
+    }
+
+    #[test]
+    fn motor_deadband_ignores_small_changes() {
+        let t0 = Instant::now();
+        let s = motor(0.0, 0.01, 0.0);
+        let mut faders = FaderState::default();
+        faders.motor_feedback(1, 0.5, s, t0);
+        assert_eq!(faders.motor_feedback(1, 0.505, s, t0), None);
+        assert!(!faders.motors_busy());
+        assert_eq!(faders.motor_feedback(1, 0.52, s, t0), Some(0.52));
+    }
+
+    #[test]
+    fn motor_smoothing_glides_to_the_level() {
+        let t0 = Instant::now();
+        let s = motor(0.0, 0.01, 0.5);
+        let mut faders = FaderState::default();
+        faders.motor_feedback(1, 0.0, s, t0);
+        assert_eq!(faders.motor_feedback(1, 1.0, s, t0), Some(0.5));
+        assert_eq!(faders.motor_pending(t0), [(1, 0.75)]);
+        assert_eq!(faders.motor_pending(t0), [(1, 0.875)]);
+        // Steps shrink until the rest fits in the deadband
+        let mut last = 0.875;
+        while faders.motors_busy() {
+            last = faders.motor_pending(t0)[0].1;
+        }
+        assert_eq!(last, 1.0);
+    }
+
+    #[test]
+    fn touched_motors_stay_put() {
+        let t0 = Instant::now();
+        let s = motor(0.0, 0.0, 0.0);
+        let mut faders = FaderState::default();
+        faders.set_touched(1, true, t0);
+        assert_eq!(faders.motor_feedback(1, 0.5, s, t0), None);
+        assert!(faders.motor_pending(t0).is_empty());
+    }
+
+    #[test]
+    fn smoothing_without_deadband_arrives() {
+        let settings = motor(0.0, 0.0, 0.5);
+        let t0 = Instant::now();
+        for target in [0.7, 0.123, 0.55] {
+            let mut faders = FaderState::default();
+            faders.motor_feedback(1, 0.0, settings, t0);
+            let mut last = faders.motor_feedback(1, target, settings, t0);
+            for _ in 0..100 {
+                if !faders.motors_busy() {
+                    break;
+                }
+                last = faders.motor_pending(t0).first().map(|&(_, p)| p);
+            }
+            assert!(!faders.motors_busy(), "stuck short of {}", target);
+            assert_eq!(last, Some(target));
+        }
+    }
+}
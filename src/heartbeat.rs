@@ -0,0 +1,156 @@
+//! `/eos/ping` round trips: each ping carries a sequence number that Eos echoes back
+//! in `/eos/out/ping`, which gives us RTT, jitter and packet loss per console.
+use rosc::{OscMessage, OscPacket, OscType};
+use std::collections::{HashMap, VecDeque};
+use tokio::time::{Duration, Instant};
+
+pub const PING_INTERVAL: Duration = Duration::from_secs(2);
+/// A ping without a reply after this long counts as lost
+const PING_TIMEOUT: Duration = Duration::from_secs(4);
+/// Loss is reported over the most recent pings
+const LOSS_WINDOW: usize = 20;
+const PING_TAG: &str = "BridgeSync";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LinkStats {
+    /// Latest round trip, `None` until Eos has answered once
+    pub rtt_ms: Option<f32>,
+    pub jitter_ms: f32,
+    pub loss_pct: f32,
+}
+
+#[derive(Debug, Default)]
+pub struct PingTracker {
+    next_seq: i32,
+    pending: HashMap<i32, Instant>,
+    last_rtt_ms: Option<f32>,
+    jitter_ms: f32,
+    outcomes: VecDeque<bool>,
+}
+
+impl PingTracker {
+    /// Builds the next ping and starts timing it
+    pub fn next_ping(&mut self, now: Instant) -> OscPacket {
+        self.expire(now);
+        let seq = self.next_seq;
+        self.next_seq = self.next_seq.wrapping_add(1);
+        self.pending.insert(seq, now);
+        OscPacket::Message(OscMessage {
+            addr: "/eos/ping".into(),
+            args: vec![OscType::String(PING_TAG.into()), OscType::Int(seq)],
+        })
+    }
+
+    /// Returns true if `msg` answers one of our pings
+    pub fn handle_reply(&mut self, msg: &OscMessage, now: Instant) -> bool {
+        if msg.addr != "/eos/out/ping" {
+            return false;
+        }
+        let seq = match msg.args.as_slice() {
+            [OscType::String(tag), OscType::Int(seq), ..] if tag == PING_TAG => *seq,
+            _ => return false,
+        };
+        let Some(sent) = self.pending.remove(&seq) else {
+            return false;
+        };
+        let rtt_ms = now.duration_since(sent).as_secs_f32() * 1000.0;
+        // RFC 3550 style smoothed jitter
+        if let Some(prev) = self.last_rtt_ms {
+            self.jitter_ms += ((rtt_ms - prev).abs() - self.jitter_ms) / 16.0;
+        }
+        self.last_rtt_ms = Some(rtt_ms);
+        self.record(true);
+        true
+    }
+
+    /// `None` until a ping has been answered or has expired
+    pub fn stats(&self) -> Option<LinkStats> {
+        if self.outcomes.is_empty() {
+            return None;
+        }
+        let lost = self.outcomes.iter().filter(|ok| !**ok).count();
+        Some(LinkStats {
+            rtt_ms: self.last_rtt_ms,
+            jitter_ms: self.jitter_ms,
+            loss_pct: 100.0 * lost as f32 / self.outcomes.len() as f32,
+        })
+    }
+
+    fn expire(&mut self, now: Instant) {
+        let before = self.pending.len();
+        self.pending
+            .retain(|_, sent| now.duration_since(*sent) < PING_TIMEOUT);
+        for _ in self.pending.len()..before {
+            self.record(false);
+        }
+    }
+
+    fn record(&mut self, ok: bool) {
+        if self.outcomes.len() == LOSS_WINDOW {
+            self.outcomes.pop_front();
+        }
+        self.outcomes.push_back(ok);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reply(tag: &str, seq: i32) -> OscMessage {
+        OscMessage {
+            addr: "/eos/out/ping".into(),
+            args: vec![OscType::String(tag.into()), OscType::Int(seq)],
+        }
+    }
+
+    fn ms(t0: Instant, n: u64) -> Instant {
+        t0 + Duration::from_millis(n)
+    }
+
+    #[test]
+    fn replies_match_their_ping() {
+        let t0 = Instant::now();
+        let mut t = PingTracker::default();
+        t.next_ping(t0);
+        t.next_ping(ms(t0, 10));
+        assert!(!t.handle_reply(&reply("Other", 0), ms(t0, 20)));
+        assert!(!t.handle_reply(&reply(PING_TAG, 7), ms(t0, 20)));
+        assert!(t.handle_reply(&reply(PING_TAG, 1), ms(t0, 30)));
+        // Each ping is answered once
+        assert!(!t.handle_reply(&reply(PING_TAG, 1), ms(t0, 40)));
+        let stats = t.stats().unwrap();
+        assert_eq!(stats.rtt_ms, Some(20.0));
+        assert_eq!(stats.loss_pct, 0.0);
+    }
+
+    #[test]
+    fn jitter_follows_rtt_changes() {
+        let t0 = Instant::now();
+        let mut t = PingTracker::default();
+        t.next_ping(t0);
+        t.handle_reply(&reply(PING_TAG, 0), ms(t0, 10));
+        t.next_ping(ms(t0, 100));
+        t.handle_reply(&reply(PING_TAG, 1), ms(t0, 130));
+        let stats = t.stats().unwrap();
+        assert_eq!(stats.rtt_ms, Some(30.0));
+        assert!((stats.jitter_ms - 20.0 / 16.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn unanswered_pings_count_as_loss() {
+        let t0 = Instant::now();
+        let mut t = PingTracker::default();
+        assert_eq!(t.stats(), None);
+        t.next_ping(t0);
+        t.next_ping(ms(t0, 2000));
+        assert_eq!(t.stats(), None);
+        // The first ping runs out when the third goes out
+        t.next_ping(ms(t0, 4000));
+        let stats = t.stats().unwrap();
+        assert_eq!(stats.rtt_ms, None);
+        assert_eq!(stats.loss_pct, 100.0);
+        // A late reply to an expired ping doesn't count
+        assert!(!t.handle_reply(&reply(PING_TAG, 0), ms(t0, 4100)));
+    }
+}
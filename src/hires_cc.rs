@@ -0,0 +1,226 @@
+//! 14-bit Control Change (MSB/LSB pairs) and NRPN assembly for high-resolution controllers
+use crate::config::{Config, MidiEventType};
+
+const NRPN_PARAM_MSB: u8 = 99;
+const NRPN_PARAM_LSB: u8 = 98;
+const DATA_ENTRY_MSB: u8 = 6;
+const DATA_ENTRY_LSB: u8 = 38;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HiResInput {
+    /// Not a 14-bit message, decode it as a plain 7-bit CC
+    Passthrough,
+    /// Part of a 14-bit message that produced no value yet
+    Pending,
+    /// Assembled (event type, CC or NRPN parameter number, normalized value)
+    Value(MidiEventType, u16, f32),
+}
+
+/// Assembles CC pairs and NRPN sequences per MIDI channel.
+/// Only CC numbers declared by a `ControlChange14` mapping are treated as MSB/LSB pairs,
+/// and NRPN controllers are only consumed when an `Nrpn` mapping exists.
+/// Values go out on the LSB, so a move across a 7-bit boundary never steps backwards;
+/// a controller that has never sent an LSB is taken as 7-bit and goes out on the MSB.
+#[derive(Debug)]
+pub struct HiResDecoder {
+    cc14: [bool; 32],
+    nrpn: bool,
+    cc_msb: [[u8; 32]; 16],
+    cc_lsb_seen: [[bool; 32]; 16],
+    nrpn_param: [(u8, u8); 16],
+    nrpn_msb: [u8; 16],
+    nrpn_lsb_seen: [bool; 16],
+}
+
+impl HiResDecoder {
+    pub fn from_config(cfg: &Config) -> Self {
+        let mut cc14 = [false; 32];
+        let mut nrpn = false;
+        for m in &cfg.mappings {
+            match m.event_type {
+                MidiEventType::ControlChange14 if m.data_number < 32 => {
+                    cc14[m.data_number as usize] = true
+                }
+                MidiEventType::Nrpn => nrpn = true,
+                _ => {}
+            }
+        }
+        HiResDecoder {
+            cc14,
+            nrpn,
+            cc_msb: [[0; 32]; 16],
+            cc_lsb_seen: [[false; 32]; 16],
+            nrpn_param: [(0x7F, 0x7F); 16],
+            nrpn_msb: [0; 16],
+            nrpn_lsb_seen: [false; 16],
+        }
+    }
+
+    pub fn feed(&mut self, msg: &[u8]) -> HiResInput {
+        if msg.len() < 3 || msg[0] & 0xF0 != 0xB0 {
+            return HiResInput::Passthrough;
+        }
+        let ch = (msg[0] & 0x0F) as usize;
+        let (cc, val) = (msg[1], msg[2]);
+
+        if self.nrpn {
+            match cc {
+                NRPN_PARAM_MSB => {
+                    self.nrpn_param[ch].0 = val;
+                    return HiResInput::Pending;
+                }
+                NRPN_PARAM_LSB => {
+                    self.nrpn_param[ch].1 = val;
+                    return HiResInput::Pending;
+                }
+                DATA_ENTRY_MSB | DATA_ENTRY_LSB => {
+                    let (pm, pl) = self.nrpn_param[ch];
+                    // 127/127 is the "null" parameter, data entry is meaningless then
+                    if pm == 0x7F && pl == 0x7F {
+                        return HiResInput::Pending;
+                    }
+                    let lsb = if cc == DATA_ENTRY_MSB {
+                        self.nrpn_msb[ch] = val;
+                        if self.nrpn_lsb_seen[ch] {
+                            return HiResInput::Pending;
+                        }
+                        0
+                    } else {
+                        self.nrpn_lsb_seen[ch] = true;
+                        val
+                    };
+                    let param = (pm as u16) << 7 | pl as u16;
+                    let value = (self.nrpn_msb[ch] as u16) << 7 | lsb as u16;
+                    return HiResInput::Value(MidiEventType::Nrpn, param, value as f32 / 16383.0);
+                }
+                _ => {}
+            }
+        }
+
+        match cc {
+            0..=31 if self.cc14[cc as usize] => {
+                self.cc_msb[ch][cc as usize] = val;
+                if self.cc_lsb_seen[ch][cc as usize] {
+                    return HiResInput::Pending;
+                }
+                let value = (val as u16) << 7;
+                HiResInput::Value(
+                    MidiEventType::ControlChange14,
+                    cc as u16,
+                    value as f32 / 16383.0,
+                )
+            }
+            32..=63 if self.cc14[(cc - 32) as usize] => {
+                let num = cc - 32;
+                self.cc_lsb_seen[ch][num as usize] = true;
+                let value = (self.cc_msb[ch][num as usize] as u16) << 7 | val as u16;
+                HiResInput::Value(
+                    MidiEventType::ControlChange14,
+                    num as u16,
+                    value as f32 / 16383.0,
+                )
+            }
+            _ => HiResInput::Passthrough,
+        }
+    }
+}
+
+/// MSB/LSB pair for a 14-bit CC (CC `number` and `number + 32`)
+pub fn cc14_message(channel: u8, number: u8, value: u16) -> [[u8; 3]; 2] {
+    let value = value.min(0x3FFF);
+    [
+        [0xB0 | channel, number, (value >> 7) as u8],
+        [0xB0 | channel, number + 32, (value & 0x7F) as u8],
+    ]
+}
+
+/// Full NRPN write: parameter select followed by data entry MSB/LSB
+pub fn nrpn_message(channel: u8, param: u16, value: u16) -> [[u8; 3]; 4] {
+    let value = value.min(0x3FFF);
+    let status = 0xB0 | channel;
+    [
+        [status, NRPN_PARAM_MSB, ((param >> 7) & 0x7F) as u8],
+        [status, NRPN_PARAM_LSB, (param & 0x7F) as u8],
+        [status, DATA_ENTRY_MSB, (value >> 7) as u8],
+        [status, DATA_ENTRY_LSB, (value & 0x7F) as u8],
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::MidiOscMapping;
+
+    fn decoder(event_type: MidiEventType, data_number: u8) -> HiResDecoder {
+        let mut cfg = Config::default();
+        let mut m: MidiOscMapping = cfg.mappings[0].clone();
+        m.event_type = event_type;
+        m.data_number = data_number;
+        m.nrpn_parameter = Some(0x81);
+        cfg.mappings = vec![m];
+        HiResDecoder::from_config(&cfg)
+    }
+
+    fn raw(input: HiResInput) -> Option<u16> {
+        match input {
+            HiResInput::Value(_, _, v) => Some((v * 16383.0).round() as u16),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn cc14_goes_out_on_the_lsb() {
+        let mut d = decoder(MidiEventType::ControlChange14, 7);
+        assert_eq!(
+            d.feed(&[0xB0, 7, 5]),
+            HiResInput::Value(MidiEventType::ControlChange14, 7, 640.0 / 16383.0)
+        );
+        assert_eq!(raw(d.feed(&[0xB0, 39, 2])), Some(642));
+        // Moving down across a 7-bit boundary: no step back to 4 << 7
+        assert_eq!(d.feed(&[0xB0, 7, 4]), HiResInput::Pending);
+        assert_eq!(raw(d.feed(&[0xB0, 39, 127])), Some(639));
+        assert_eq!(d.feed(&[0xB0, 8, 1]), HiResInput::Passthrough);
+    }
+
+    #[test]
+    fn cc14_is_per_channel() {
+        let mut d = decoder(MidiEventType::ControlChange14, 7);
+        d.feed(&[0xB0, 7, 5]);
+        d.feed(&[0xB0, 39, 0]);
+        // Channel 2 has never sent an LSB, so its MSB alone is the value
+        assert_eq!(raw(d.feed(&[0xB1, 7, 3])), Some(384));
+        assert_eq!(d.feed(&[0xB0, 7, 6]), HiResInput::Pending);
+    }
+
+    #[test]
+    fn nrpn_assembles_parameter_and_value() {
+        let mut d = decoder(MidiEventType::Nrpn, 0);
+        assert_eq!(d.feed(&[0xB0, 6, 10]), HiResInput::Pending);
+        assert_eq!(d.feed(&[0xB0, 99, 1]), HiResInput::Pending);
+        assert_eq!(d.feed(&[0xB0, 98, 1]), HiResInput::Pending);
+        let first = d.feed(&[0xB0, 6, 10]);
+        assert!(matches!(
+            first,
+            HiResInput::Value(MidiEventType::Nrpn, 0x81, _)
+        ));
+        assert_eq!(raw(first), Some(1280));
+        assert_eq!(raw(d.feed(&[0xB0, 38, 3])), Some(1283));
+        assert_eq!(d.feed(&[0xB0, 6, 9]), HiResInput::Pending);
+        assert_eq!(raw(d.feed(&[0xB0, 38, 127])), Some(1279));
+    }
+
+    #[test]
+    fn messages_round_trip_through_the_decoder() {
+        let mut d = decoder(MidiEventType::ControlChange14, 7);
+        let [msb, lsb] = cc14_message(3, 7, 9000);
+        assert_eq!(raw(d.feed(&msb)), Some(9000 & !0x7F));
+        assert_eq!(raw(d.feed(&lsb)), Some(9000));
+
+        let mut d = decoder(MidiEventType::Nrpn, 0);
+        let mut last = None;
+        for m in nrpn_message(2, 0x81, 4321) {
+            last = raw(d.feed(&m)).or(last);
+        }
+        assert_eq!(last, Some(4321));
+    }
+}
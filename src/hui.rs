@@ -0,0 +1,172 @@
+//! Mackie HUI protocol: zone/port switches, 14-bit faders, scribble strips and ping
+
+/// Host ping, the surface answers with `90 00 7F` and goes offline without it
+pub const PING: [u8; 3] = [0x90, 0x00, 0x00];
+/// HUI wants a ping roughly once per second
+pub const PING_INTERVAL_MS: u64 = 1000;
+
+const ZONE_SELECT_CC: u8 = 0x0F;
+const PORT_CC: u8 = 0x2F;
+/// Host to surface counterparts, for switch LEDs
+const LED_ZONE_CC: u8 = 0x0C;
+const LED_PORT_CC: u8 = 0x2C;
+/// Strip zones 0-7 use port 0 for fader touch
+const TOUCH_PORT: u8 = 0x00;
+const SYSEX_HEADER: [u8; 5] = [0xF0, 0x00, 0x00, 0x66, 0x05];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HuiEvent {
+    /// Fader on strip 0-7, full 14-bit value
+    Fader(u8, u16),
+    /// Fader touch on strip 0-7
+    Touch(u8, bool),
+    /// Any other switch, identified by zone and port
+    Switch { zone: u8, port: u8, pressed: bool },
+}
+
+/// Packs a zone/port pair into the `data_number` used by mappings
+pub fn switch_number(zone: u8, port: u8) -> u8 {
+    (zone << 3) | (port & 0x07)
+}
+
+/// Turns the raw HUI CC stream into events. HUI is stateful:
+/// a zone select precedes each port message and fader MSB precedes LSB.
+#[derive(Debug, Default)]
+pub struct HuiDecoder {
+    zone: Option<u8>,
+    fader_msb: [u8; 8],
+}
+
+impl HuiDecoder {
+    pub fn feed(&mut self, msg: &[u8]) -> Option<HuiEvent> {
+        if msg.len() < 3 || msg[0] & 0xF0 != 0xB0 {
+            return None;
+        }
+        let (cc, val) = (msg[1], msg[2]);
+        match cc {
+            0x00..=0x07 => {
+                self.fader_msb[cc as usize] = val;
+                None
+            }
+            0x20..=0x27 => {
+                let strip = cc - 0x20;
+                let value = ((self.fader_msb[strip as usize] as u16) << 7) | val as u16;
+                Some(HuiEvent::Fader(strip, value))
+            }
+            ZONE_SELECT_CC => {
+                self.zone = Some(val);
+                None
+            }
+            PORT_CC => {
+                let zone = self.zone?;
+                // Bit 6 is the press flag, ports only go up to 7
+                let port = val & 0x07;
+                let pressed = val & 0x40 != 0;
+                if zone < 8 && port == TOUCH_PORT {
+                    Some(HuiEvent::Touch(zone, pressed))
+                } else {
+                    Some(HuiEvent::Switch {
+                        zone,
+                        port,
+                        pressed,
+                    })
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Fader move for strip 0-7 as an MSB/LSB CC pair
+pub fn fader_message(strip: u8, value: u16) -> [[u8; 3]; 2] {
+    let value = value.min(0x3FFF);
+    [
+        [0xB0, strip, (value >> 7) as u8],
+        [0xB0, 0x20 + strip, (value & 0x7F) as u8],
+    ]
+}
+
+/// Turns the LED of a switch (numbered as by `switch_number`) on or off
+pub fn led_message(switch: u8, on: bool) -> [[u8; 3]; 2] {
+    let port = (switch & 0x07) | if on { 0x40 } else { 0x00 };
+    [[0xB0, LED_ZONE_CC, switch >> 3], [0xB0, LED_PORT_CC, port]]
+}
+
+/// Sysex for one 4-character scribble strip (strip 0-7)
+pub fn label_sysex(strip: u8, label: &str) -> Vec<u8> {
+    let mut sysex = SYSEX_HEADER.to_vec();
+    sysex.extend_from_slice(&[0x00, 0x10, strip]);
+
+    // HUI strips only hold 4 characters
+    let display_text = format!("{: ^4}", label);
+    sysex.extend(display_text.bytes().take(4));
+    sysex.push(0xF7);
+    sysex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn faders_join_msb_and_lsb() {
+        let mut hui = HuiDecoder::default();
+        assert_eq!(hui.feed(&[0xB0, 0x02, 0x40]), None);
+        assert_eq!(
+            hui.feed(&[0xB0, 0x22, 0x05]),
+            Some(HuiEvent::Fader(2, 0x2005))
+        );
+        // A new LSB alone keeps the last MSB
+        assert_eq!(
+            hui.feed(&[0xB0, 0x22, 0x06]),
+            Some(HuiEvent::Fader(2, 0x2006))
+        );
+    }
+
+    #[test]
+    fn ports_follow_the_zone_select() {
+        let mut hui = HuiDecoder::default();
+        // No zone yet
+        assert_eq!(hui.feed(&[0xB0, PORT_CC, 0x43]), None);
+        hui.feed(&[0xB0, ZONE_SELECT_CC, 0x0A]);
+        assert_eq!(
+            hui.feed(&[0xB0, PORT_CC, 0x43]),
+            Some(HuiEvent::Switch {
+                zone: 10,
+                port: 3,
+                pressed: true
+            })
+        );
+        assert_eq!(
+            hui.feed(&[0xB0, PORT_CC, 0x03]),
+            Some(HuiEvent::Switch {
+                zone: 10,
+                port: 3,
+                pressed: false
+            })
+        );
+        assert_eq!(switch_number(10, 3), 83);
+    }
+
+    #[test]
+    fn strip_port_zero_is_touch() {
+        let mut hui = HuiDecoder::default();
+        hui.feed(&[0xB0, ZONE_SELECT_CC, 0x04]);
+        assert_eq!(
+            hui.feed(&[0xB0, PORT_CC, 0x40]),
+            Some(HuiEvent::Touch(4, true))
+        );
+        assert_eq!(
+            hui.feed(&[0xB0, PORT_CC, 0x00]),
+            Some(HuiEvent::Touch(4, false))
+        );
+    }
+
+    #[test]
+    fn leds_use_the_switch_zone_and_port() {
+        assert_eq!(
+            led_message(switch_number(10, 3), true),
+            [[0xB0, LED_ZONE_CC, 10], [0xB0, LED_PORT_CC, 0x43]]
+        );
+    }
+}
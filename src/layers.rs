@@ -0,0 +1,89 @@
+//! Shift/modifier layers: a layer key selects which mappings a control fires,
+//! either while it is held or, latched, until it is pressed again.
+use std::sync::{Arc, Mutex};
+
+pub type SharedLayers = Arc<Mutex<LayerState>>;
+
+#[derive(Debug, Default)]
+pub struct LayerState {
+    /// Index into `Config::layers`, `None` for the base layer
+    active: Option<usize>,
+    /// LED changes not yet sent to the surface, by layer key
+    leds: Vec<(usize, bool)>,
+}
+
+impl LayerState {
+    pub fn shared() -> SharedLayers {
+        Arc::new(Mutex::new(LayerState::default()))
+    }
+
+    pub fn active(&self) -> Option<usize> {
+        self.active
+    }
+
+    /// Press or release of layer key `idx`
+    pub fn key(&mut self, idx: usize, latch: bool, pressed: bool) {
+        let next = match (latch, pressed) {
+            (true, true) if self.active == Some(idx) => None,
+            (_, true) => Some(idx),
+            (false, false) if self.active == Some(idx) => None,
+            _ => return,
+        };
+        if next == self.active {
+            return;
+        }
+        self.leds.extend(self.active.map(|old| (old, false)));
+        self.leds.extend(next.map(|new| (new, true)));
+        self.active = next;
+    }
+
+    pub fn take_leds(&mut self) -> Vec<(usize, bool)> {
+        std::mem::take(&mut self.leds)
+    }
+
+    pub fn leds_pending(&self) -> bool {
+        !self.leds.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn held_key_takes_over_a_latched_layer() {
+        let mut layers = LayerState::default();
+        layers.key(0, true, true);
+        layers.key(0, true, false);
+        assert_eq!(layers.active(), Some(0));
+        layers.key(1, false, true);
+        assert_eq!(layers.active(), Some(1));
+        assert_eq!(layers.take_leds(), vec![(0, true), (0, false), (1, true)]);
+        layers.key(1, false, false);
+        assert_eq!(layers.active(), None);
+        assert_eq!(layers.take_leds(), vec![(1, false)]);
+    }
+
+    #[test]
+    fn pressing_a_latched_key_again_unlatches() {
+        let mut layers = LayerState::default();
+        layers.key(0, true, true);
+        layers.key(0, true, false);
+        layers.key(0, true, true);
+        layers.key(0, true, false);
+        assert_eq!(layers.active(), None);
+        assert_eq!(layers.take_leds(), vec![(0, true), (0, false)]);
+    }
+
+    #[test]
+    fn releasing_an_inactive_key_changes_nothing() {
+        let mut layers = LayerState::default();
+        layers.key(1, false, false);
+        assert_eq!(layers.active(), None);
+        layers.key(0, false, true);
+        layers.key(1, false, false);
+        assert_eq!(layers.active(), Some(0));
+        assert_eq!(layers.take_leds(), vec![(0, true)]);
+        assert!(!layers.leds_pending());
+    }
+}
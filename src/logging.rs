@@ -0,0 +1,215 @@
+//! Leveled, timestamped log entries, written to a rotating file next to the config
+//! and forwarded to the GUI log panel.
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+
+/// Rotate once the file grows past this size
+const MAX_FILE_BYTES: u64 = 1024 * 1024;
+/// Number of rotated files kept (bridge.log.1 .. bridge.log.N)
+const KEEP_FILES: u32 = 3;
+
+static LOG_FILE: Mutex<Option<RotatingFile>> = Mutex::new(None);
+/// Entries below this level only reach the log panel, see `set_file_level`
+static FILE_LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Info as u8);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Serialize, Deserialize)]
+pub enum LogLevel {
+    Debug,
+    #[default]
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    pub const ALL: [LogLevel; 4] = [
+        LogLevel::Debug,
+        LogLevel::Info,
+        LogLevel::Warn,
+        LogLevel::Error,
+    ];
+}
+
+impl std::fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LogLevel::Debug => write!(f, "DEBUG"),
+            LogLevel::Info => write!(f, "INFO"),
+            LogLevel::Warn => write!(f, "WARN"),
+            LogLevel::Error => write!(f, "ERROR"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    /// UTC, `YYYY-MM-DD HH:MM:SS.mmm`
+    pub timestamp: String,
+    pub level: LogLevel,
+    pub message: String,
+}
+
+impl std::fmt::Display for LogEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {:<5} {}", self.timestamp, self.level, self.message)
+    }
+}
+
+/// Lowest level written to `bridge.log`
+pub fn set_file_level(level: LogLevel) {
+    FILE_LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+/// Creates an entry and appends it to the log file if its level is high enough
+pub fn entry(level: LogLevel, message: impl Into<String>) -> LogEntry {
+    let entry = LogEntry {
+        timestamp: timestamp_now(),
+        level,
+        message: message.into(),
+    };
+    if (level as u8) < FILE_LEVEL.load(Ordering::Relaxed) {
+        return entry;
+    }
+    if let Ok(mut file) = LOG_FILE.lock() {
+        if file.is_none() {
+            *file = RotatingFile::open();
+        }
+        if let Some(f) = file.as_mut() {
+            f.write_line(&entry.to_string());
+        }
+    }
+    entry
+}
+
+/// Cheap handle for code that can't reach the GUI channel directly
+/// (midir callback, transport tasks); the bridge forwards what it receives.
+#[derive(Clone)]
+pub struct Logger {
+    tx: mpsc::UnboundedSender<LogEntry>,
+    /// Keys already logged by `debug_once`
+    seen: Arc<Mutex<HashSet<String>>>,
+}
+
+impl Logger {
+    pub fn new() -> (Self, mpsc::UnboundedReceiver<LogEntry>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let seen = Arc::default();
+        (Logger { tx, seen }, rx)
+    }
+
+    pub fn log(&self, level: LogLevel, message: impl Into<String>) {
+        let _ = self.tx.send(entry(level, message));
+    }
+
+    pub fn debug(&self, message: impl Into<String>) {
+        self.log(LogLevel::Debug, message);
+    }
+
+    /// Logs at debug level the first time `key` comes up, for things that repeat endlessly
+    pub fn debug_once(&self, key: &str, message: impl FnOnce() -> String) {
+        let first = self
+            .seen
+            .lock()
+            .is_ok_and(|mut seen| seen.insert(key.to_string()));
+        if first {
+            self.debug(message());
+        }
+    }
+
+    pub fn info(&self, message: impl Into<String>) {
+        self.log(LogLevel::Info, message);
+    }
+
+    pub fn warn(&self, message: impl Into<String>) {
+        self.log(LogLevel::Warn, message);
+    }
+
+    pub fn error(&self, message: impl Into<String>) {
+        self.log(LogLevel::Error, message);
+    }
+}
+
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    fn open() -> Option<Self> {
+        let cfg_path = confy::get_configuration_file_path("eos-midi-bridge", None).ok()?;
+        let dir = cfg_path.parent()?;
+        fs::create_dir_all(dir).ok()?;
+        let path = dir.join("bridge.log");
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .ok()?;
+        let size = file.metadata().map(|m| m.len()).unwrap_or(0);
+        Some(RotatingFile { path, file, size })
+    }
+
+    fn write_line(&mut self, line: &str) {
+        if self.size >= MAX_FILE_BYTES {
+            self.rotate();
+        }
+        if writeln!(self.file, "{}", line).is_ok() {
+            self.size += line.len() as u64 + 1;
+        }
+    }
+
+    fn rotate(&mut self) {
+        let rotated = |n: u32| self.path.with_extension(format!("log.{}", n));
+        for n in (1..KEEP_FILES).rev() {
+            let _ = fs::rename(rotated(n), rotated(n + 1));
+        }
+        let _ = fs::rename(&self.path, rotated(1));
+        if let Ok(file) = File::create(&self.path) {
+            self.file = file;
+            self.size = 0;
+        }
+    }
+}
+
+/// Current UTC time as `YYYY-MM-DD HH:MM:SS.mmm`
+pub fn timestamp_now() -> String {
+    format_timestamp(SystemTime::now())
+}
+
+fn format_timestamp(t: SystemTime) -> String {
+    let d = t.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = d.as_secs();
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+    let (y, m, day) = civil_from_days(days as i64);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:03}",
+        y,
+        m,
+        day,
+        rem / 3600,
+        (rem % 3600) / 60,
+        rem % 60,
+        d.subsec_millis()
+    )
+}
+
+/// Days since 1970-01-01 to a (year, month, day) date, after Howard Hinnant
+fn civil_from_days(z: i64) -> (i64, u32, u32) {
+    let z = z + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    (yoe + era * 400 + i64::from(m <= 2), m, d)
+}
@@ -1,22 +1,55 @@
 #![windows_subsystem = "windows"]
-use iced::widget::{button, column, container, pick_list, progress_bar, row, text, text_input};
+use iced::widget::{
+    button, checkbox, column, container, pick_list, progress_bar, row, scrollable, text, text_input,
+};
 use iced::{
     window, Alignment, Application, Color, Command, Element, Event, Length, Settings, Theme,
 };
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 mod config;
+mod device_detect;
+mod discovery;
+mod faders;
+mod heartbeat;
+mod hires_cc;
+mod hui;
+mod layers;
+mod logging;
 mod midi_osc_logic;
+mod monitor;
+mod osc_pattern;
+mod osc_transport;
+mod routing;
+mod sequence;
+mod session;
+mod template;
+mod throttle;
 
-use config::Config;
-use midi_osc_logic::{bridge_subscription, BridgeEvent};
+use config::{Config, DeviceProfile, EosTarget, MidiEventType, OscTransport};
+use device_detect::DetectedDevice;
+use discovery::DiscoveredConsole;
+use faders::PickupDirection;
+use logging::{LogEntry, LogLevel};
+use midi_osc_logic::{bridge_subscription, BridgeEvent, BridgeShared, MidiQueueStats};
+use monitor::TrafficEntry;
+use session::ReplaySummary;
 
 const EOS_BG: Color = Color::from_rgb(0.05, 0.05, 0.05);
 const EOS_SURFACE: Color = Color::from_rgb(0.15, 0.15, 0.15);
 const EOS_GOLD: Color = Color::from_rgb(0.85, 0.65, 0.15);
 const EOS_AMBER: Color = Color::from_rgb(0.9, 0.4, 0.0);
 const EOS_TEXT: Color = Color::from_rgb(0.9, 0.9, 0.9);
+const EOS_RED: Color = Color::from_rgb(0.9, 0.25, 0.2);
+
+/// Entries kept in the log panel, the file keeps everything
+const LOG_PANEL_CAPACITY: usize = 500;
+/// Messages kept in the traffic monitor
+const MONITOR_CAPACITY: usize = 1000;
 
 pub fn main() -> iced::Result {
     EosBridge::run(Settings {
@@ -35,6 +68,8 @@ struct EosBridge {
     eos_ip_value: String,
     eos_port_value: String,
     listen_port_value: String,
+    eos_user_value: String,
+    backup_ips_value: String,
 
     // MIDI ports
     in_ports: Vec<String>,
@@ -42,26 +77,98 @@ struct EosBridge {
     selected_in: Option<String>,
     selected_out: Option<String>,
 
+    // controller detection
+    detecting: bool,
+    detected_devices: Vec<DetectedDevice>,
+
+    // console discovery
+    discovering: bool,
+    discovered_consoles: Vec<DiscoveredConsole>,
+
     // bridge state
     is_running: bool,
+    shared: BridgeShared,
+    active_console_idx: usize,
+    console_alive: Vec<bool>,
     last_heartbeat: Option<Instant>,
+    link_stats: Option<heartbeat::LinkStats>,
+    midi_queue: Option<MidiQueueStats>,
     fader_levels: [f32; 9],
     fader_labels: [String; 9],
+    pickup_hints: [Option<PickupDirection>; 9],
+
+    // log panel
+    log_entries: VecDeque<LogEntry>,
+    log_min_level: LogLevel,
+    log_filter: String,
+
+    // traffic monitor
+    show_monitor: bool,
+    monitor_paused: bool,
+    traffic: VecDeque<TrafficEntry>,
+    traffic_filter: String,
+
+    // session recording and replay
+    recording: Option<PathBuf>,
+    replay_path_value: String,
+    replaying: bool,
 }
 
 #[derive(Debug, Clone)]
 enum Message {
     InPortSelected(String),
     OutPortSelected(String),
+    ProfileSelected(DeviceProfile),
+    DetectControllers,
+    ControllersDetected(Vec<DetectedDevice>),
+    DetectedDeviceSelected(usize),
     ToggleBridge,
     EventOccurred(BridgeEvent),
 
     EosIpChanged(String),
+    DiscoverConsoles,
+    ConsolesDiscovered(Vec<DiscoveredConsole>),
     EosPortChanged(String),
     ListenPortChanged(String),
+    TransportSelected(OscTransport),
+    EosUserChanged(String),
+    BackupIpsChanged(String),
+    SwitchConsole(usize),
+    PrefixUserToggled(bool),
+    PickupToggled(bool),
     SaveConfig,
     SaveResult(Result<(), String>),
     WindowClosed,
+
+    LogLevelSelected(LogLevel),
+    LogFilterChanged(String),
+    ClearLog,
+
+    ToggleMonitor,
+    PauseMonitor,
+    ClearMonitor,
+    TrafficFilterChanged(String),
+
+    ToggleRecording,
+    ReplayPathChanged(String),
+    ReplaySession,
+    ReplayFinished(Result<ReplaySummary, String>),
+}
+
+impl EosBridge {
+    fn push_log(&mut self, entry: LogEntry) {
+        if self.log_entries.len() == LOG_PANEL_CAPACITY {
+            self.log_entries.pop_front();
+        }
+        self.log_entries.push_back(entry);
+    }
+
+    /// The engine only builds traffic entries while someone is looking at them
+    fn sync_monitor_enabled(&self) {
+        self.shared
+            .monitor_enabled
+            .store(self.show_monitor && !self.monitor_paused, Ordering::Relaxed);
+    }
 }
 
 impl Application for EosBridge {
@@ -72,6 +179,7 @@ impl Application for EosBridge {
 
     fn new(_flags: ()) -> (Self, Command<Message>) {
         let cfg: Config = confy::load("eos-midi-bridge", None).unwrap_or_default();
+        logging::set_file_level(cfg.log_file_level);
 
         let midi_in = midir::MidiInput::new("Eos-In-Probe").unwrap();
         let midi_out = midir::MidiOutput::new("Eos-Out-Probe").unwrap();
@@ -92,15 +200,42 @@ impl Application for EosBridge {
                 eos_ip_value: cfg.eos_ip.clone(),
                 eos_port_value: cfg.eos_port.to_string(),
                 listen_port_value: cfg.listen_port.to_string(),
+                eos_user_value: cfg.eos_user.map(|u| u.to_string()).unwrap_or_default(),
+                backup_ips_value: cfg
+                    .backup_targets
+                    .iter()
+                    .map(|t| t.addr())
+                    .collect::<Vec<_>>()
+                    .join(", "),
                 config: Arc::new(cfg),
                 in_ports,
                 out_ports,
                 selected_in: None,
                 selected_out: None,
+                detecting: false,
+                detected_devices: vec![],
+                discovering: false,
+                discovered_consoles: vec![],
                 is_running: false,
+                shared: BridgeShared::default(),
+                active_console_idx: 0,
+                console_alive: vec![],
                 last_heartbeat: None,
+                link_stats: None,
+                midi_queue: None,
                 fader_levels: [0.0; 9],
                 fader_labels: std::array::from_fn(|_| String::from("...")),
+                pickup_hints: [None; 9],
+                log_entries: VecDeque::new(),
+                log_min_level: LogLevel::Info,
+                log_filter: String::new(),
+                show_monitor: false,
+                monitor_paused: false,
+                traffic: VecDeque::new(),
+                traffic_filter: String::new(),
+                recording: None,
+                replay_path_value: String::new(),
+                replaying: false,
             },
             Command::none(),
         )
@@ -114,12 +249,66 @@ impl Application for EosBridge {
         match message {
             Message::InPortSelected(port) => self.selected_in = Some(port),
             Message::OutPortSelected(port) => self.selected_out = Some(port),
+            Message::ProfileSelected(profile) => {
+                let mut new_cfg = (*self.config).clone();
+                new_cfg.device_profile = profile;
+                self.config = Arc::new(new_cfg);
+            }
+            Message::DetectControllers => {
+                // Detection opens every port, so never run it under a live bridge
+                if self.is_running || self.detecting {
+                    return Command::none();
+                }
+                self.detecting = true;
+                return Command::perform(
+                    async {
+                        tokio::task::spawn_blocking(device_detect::detect_controllers)
+                            .await
+                            .unwrap_or_default()
+                    },
+                    Message::ControllersDetected,
+                );
+            }
+            Message::ControllersDetected(devices) => {
+                self.detecting = false;
+                self.push_log(logging::entry(
+                    LogLevel::Info,
+                    format!("Controller detection found {} device(s)", devices.len()),
+                ));
+                self.detected_devices = devices;
+                // Auto-select the first device we recognise
+                if let Some(idx) = self
+                    .detected_devices
+                    .iter()
+                    .position(|d| d.profile.is_some())
+                {
+                    return self.update(Message::DetectedDeviceSelected(idx));
+                }
+            }
+            Message::DetectedDeviceSelected(idx) => {
+                if let Some(dev) = self.detected_devices.get(idx).cloned() {
+                    self.selected_in = Some(dev.in_port);
+                    self.selected_out = Some(dev.out_port);
+                    if let Some(profile) = dev.profile {
+                        return self.update(Message::ProfileSelected(profile));
+                    }
+                }
+            }
             Message::ToggleBridge => {
                 if self.selected_in.is_some() && self.selected_out.is_some() {
                     self.is_running = !self.is_running;
+                    // Every session starts on the primary
+                    self.shared.active_console.store(0, Ordering::Relaxed);
+                    self.active_console_idx = 0;
+                    self.console_alive.clear();
+                    self.link_stats = None;
+                    self.midi_queue = None;
+                    self.pickup_hints = [None; 9];
                 }
             }
+            Message::SwitchConsole(idx) => self.shared.active_console.store(idx, Ordering::Relaxed),
             Message::WindowClosed => {
+                self.shared.recorder.stop();
                 // If we are currently connected, clear the hardware display
                 if let (Some(out_name), true) = (&self.selected_out, self.is_running) {
                     let midi_out = midir::MidiOutput::new("Eos-Cleanup").unwrap();
@@ -129,7 +318,15 @@ impl Application for EosBridge {
                         .find(|p| midi_out.port_name(p).unwrap_or_default() == *out_name)
                     {
                         if let Ok(mut conn) = midi_out.connect(port, "cleanup") {
-                            midi_osc_logic::clear_mcu_display(&mut conn);
+                            if let Err(e) =
+                                midi_osc_logic::clear_display(&mut conn, self.config.device_profile)
+                            {
+                                // The window is closing, only the log file will see this
+                                logging::entry(
+                                    LogLevel::Warn,
+                                    format!("Clearing the display failed: {}", e),
+                                );
+                            }
                             // Brief sleep to ensure the MIDI message is sent before the process dies
                             std::thread::sleep(std::time::Duration::from_millis(1000));
                         }
@@ -141,21 +338,74 @@ impl Application for EosBridge {
             Message::EventOccurred(BridgeEvent::None) => {}
             Message::EventOccurred(event) => match event {
                 BridgeEvent::ConnectionHeartbeat => self.last_heartbeat = Some(Instant::now()),
-                BridgeEvent::FaderUpdate(i, v) => {
-                    if (i as usize) < self.fader_levels.len() {
-                        self.fader_levels[i as usize] = v;
+                BridgeEvent::Log(entry) => self.push_log(entry),
+                // Entries still in flight when the user paused are dropped too
+                BridgeEvent::Traffic(entry) if !self.monitor_paused => {
+                    if self.traffic.len() == MONITOR_CAPACITY {
+                        self.traffic.pop_front();
                     }
+                    self.traffic.push_back(entry);
                 }
-                BridgeEvent::LabelUpdate(i, l) => {
-                    if (i as usize) < self.fader_labels.len() {
-                        self.fader_labels[i as usize] = l;
-                    }
+                BridgeEvent::LinkStats(stats) => self.link_stats = Some(stats),
+                BridgeEvent::MidiQueue(stats) => self.midi_queue = Some(stats),
+                BridgeEvent::ConsoleStatus(alive) => self.console_alive = alive,
+                BridgeEvent::ActiveConsole(idx) => self.active_console_idx = idx,
+                BridgeEvent::FaderUpdate(i, v) if (i as usize) < self.fader_levels.len() => {
+                    self.fader_levels[i as usize] = v;
+                }
+                BridgeEvent::Pickup(i, dir) if (i as usize) < self.pickup_hints.len() => {
+                    self.pickup_hints[i as usize] = dir
+                }
+                BridgeEvent::LabelUpdate(i, l) if (i as usize) < self.fader_labels.len() => {
+                    self.fader_labels[i as usize] = l;
                 }
                 _ => {}
             },
             Message::EosIpChanged(s) => self.eos_ip_value = s,
+            Message::DiscoverConsoles => {
+                if self.discovering {
+                    return Command::none();
+                }
+                self.discovering = true;
+                let eos_port = self.eos_port_value.parse().unwrap_or(self.config.eos_port);
+                let listen_port = self
+                    .listen_port_value
+                    .parse()
+                    .unwrap_or(self.config.listen_port);
+                return Command::perform(
+                    discovery::discover_consoles(eos_port, listen_port),
+                    Message::ConsolesDiscovered,
+                );
+            }
+            Message::ConsolesDiscovered(consoles) => {
+                self.discovering = false;
+                self.discovered_consoles = consoles;
+            }
             Message::EosPortChanged(s) => self.eos_port_value = s,
             Message::ListenPortChanged(s) => self.listen_port_value = s,
+            Message::EosUserChanged(s) => self.eos_user_value = s,
+            Message::BackupIpsChanged(s) => self.backup_ips_value = s,
+            Message::PrefixUserToggled(on) => {
+                let mut new_cfg = (*self.config).clone();
+                new_cfg.prefix_user_commands = on;
+                self.config = Arc::new(new_cfg);
+            }
+            Message::PickupToggled(on) => {
+                let mut new_cfg = (*self.config).clone();
+                for m in new_cfg
+                    .mappings
+                    .iter_mut()
+                    .filter(|m| m.event_type == MidiEventType::PitchBend)
+                {
+                    m.pickup = on;
+                }
+                self.config = Arc::new(new_cfg);
+            }
+            Message::TransportSelected(t) => {
+                let mut new_cfg = (*self.config).clone();
+                new_cfg.osc_transport = t;
+                self.config = Arc::new(new_cfg);
+            }
             Message::SaveConfig => {
                 // Clone the existing config and overwrite fields from UI values
                 let mut new_cfg = (*self.config).clone();
@@ -166,6 +416,25 @@ impl Application for EosBridge {
                 if let Ok(lp) = self.listen_port_value.parse::<u16>() {
                     new_cfg.listen_port = lp;
                 }
+                // "ip" or "ip:port", comma separated, port defaults to the primary's
+                new_cfg.backup_targets = self
+                    .backup_ips_value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .map(|s| match s.rsplit_once(':') {
+                        Some((ip, port)) => EosTarget {
+                            ip: ip.to_string(),
+                            port: port.parse().unwrap_or(new_cfg.eos_port),
+                        },
+                        None => EosTarget {
+                            ip: s.to_string(),
+                            port: new_cfg.eos_port,
+                        },
+                    })
+                    .collect();
+                // An empty field means "no user"
+                new_cfg.eos_user = self.eos_user_value.trim().parse::<u16>().ok();
 
                 let cfg_clone = new_cfg.clone();
                 return Command::perform(
@@ -180,10 +449,80 @@ impl Application for EosBridge {
                 Ok(_) => {
                     let updated_cfg: Config =
                         confy::load("eos-midi-bridge", None).unwrap_or_default();
+                    logging::set_file_level(updated_cfg.log_file_level);
                     self.config = Arc::new(updated_cfg);
                 }
-                Err(e) => eprintln!("Config save error: {}", e),
+                Err(e) => self.push_log(logging::entry(LogLevel::Error, e)),
             },
+            Message::LogLevelSelected(level) => self.log_min_level = level,
+            Message::LogFilterChanged(s) => self.log_filter = s,
+            Message::ClearLog => self.log_entries.clear(),
+            Message::ToggleMonitor => {
+                self.show_monitor = !self.show_monitor;
+                self.sync_monitor_enabled();
+            }
+            Message::PauseMonitor => {
+                self.monitor_paused = !self.monitor_paused;
+                self.sync_monitor_enabled();
+            }
+            Message::ClearMonitor => self.traffic.clear(),
+            Message::TrafficFilterChanged(s) => self.traffic_filter = s,
+
+            Message::ToggleRecording => {
+                if self.recording.take().is_some() {
+                    self.shared.recorder.stop();
+                    self.push_log(logging::entry(LogLevel::Info, "Session recording stopped"));
+                } else {
+                    match self.shared.recorder.start() {
+                        Ok(path) => {
+                            self.push_log(logging::entry(
+                                LogLevel::Info,
+                                format!("Recording session to {}", path.display()),
+                            ));
+                            self.replay_path_value = path.display().to_string();
+                            self.recording = Some(path);
+                        }
+                        Err(e) => self.push_log(logging::entry(
+                            LogLevel::Error,
+                            format!("Could not start recording: {}", e),
+                        )),
+                    }
+                }
+            }
+            Message::ReplayPathChanged(s) => self.replay_path_value = s,
+            Message::ReplaySession => {
+                if self.replaying || self.replay_path_value.trim().is_empty() {
+                    return Command::none();
+                }
+                self.replaying = true;
+                let path = PathBuf::from(self.replay_path_value.trim());
+                let cfg = self.config.clone();
+                return Command::perform(
+                    async move {
+                        session::replay_session(path, cfg)
+                            .await
+                            .map_err(|e| e.to_string())
+                    },
+                    Message::ReplayFinished,
+                );
+            }
+            Message::ReplayFinished(result) => {
+                self.replaying = false;
+                let entry = match result {
+                    Ok(s) => logging::entry(
+                        LogLevel::Info,
+                        format!(
+                            "Replayed {} events: {} MIDI out, {} OSC out, report in {}",
+                            s.events,
+                            s.midi_out,
+                            s.osc_out,
+                            s.report.display()
+                        ),
+                    ),
+                    Err(e) => logging::entry(LogLevel::Error, format!("Replay failed: {}", e)),
+                };
+                self.push_log(entry);
+            }
         }
 
         Command::none()
@@ -201,8 +540,13 @@ impl Application for EosBridge {
         if self.is_running {
             if let (Some(in_p), Some(out_p)) = (&self.selected_in, &self.selected_out) {
                 subs.push(
-                    bridge_subscription(in_p.clone(), out_p.clone(), self.config.clone())
-                        .map(Message::EventOccurred),
+                    bridge_subscription(
+                        in_p.clone(),
+                        out_p.clone(),
+                        self.config.clone(),
+                        self.shared.clone(),
+                    )
+                    .map(Message::EventOccurred),
                 );
             }
         }
@@ -212,7 +556,7 @@ impl Application for EosBridge {
     fn view(&self) -> Element<'_, Message> {
         let is_connected = self
             .last_heartbeat
-            .map_or(false, |t| t.elapsed() < Duration::from_secs(7));
+            .is_some_and(|t| t.elapsed() < Duration::from_secs(7));
 
         let status_color = if self.is_running {
             if is_connected {
@@ -224,6 +568,43 @@ impl Application for EosBridge {
             Color::from_rgb(0.3, 0.3, 0.3)
         };
 
+        // Failover indicator, only worth showing with at least one backup
+        let targets = self.config.eos_targets();
+        let console_row = row(targets
+            .iter()
+            .enumerate()
+            .filter(|_| targets.len() > 1)
+            .map(|(i, t)| {
+                let alive = self.console_alive.get(i).copied().unwrap_or(false);
+                let name = if i == 0 {
+                    "PRIMARY".to_string()
+                } else {
+                    format!("BACKUP {}", i)
+                };
+                let marker = if i == self.active_console_idx {
+                    "▶ "
+                } else {
+                    ""
+                };
+                let color = if alive {
+                    EOS_GOLD
+                } else {
+                    Color::from_rgb(0.5, 0.5, 0.5)
+                };
+                button(
+                    text(format!("{}{} {}", marker, name, t.ip))
+                        .size(12)
+                        .style(color),
+                )
+                .on_press_maybe(
+                    (self.is_running && i != self.active_console_idx)
+                        .then_some(Message::SwitchConsole(i)),
+                )
+                .padding(5)
+                .into()
+            }))
+        .spacing(8);
+
         let header = container(
             row![
                 text("EOS MIDI BRIDGE").size(24).style(EOS_GOLD),
@@ -249,10 +630,38 @@ impl Application for EosBridge {
                         "WAITING FOR EOS..."
                     })
                     .size(14)
-                    .style(status_color)
+                    .style(status_color),
+                    text(match (self.is_running, self.link_stats) {
+                        (true, Some(s)) => match s.rtt_ms {
+                            Some(rtt) => format!(
+                                "RTT {:.1} ms · jitter {:.1} ms · loss {:.0}%",
+                                rtt, s.jitter_ms, s.loss_pct
+                            ),
+                            None => format!("No reply · loss {:.0}%", s.loss_pct),
+                        },
+                        _ => String::new(),
+                    })
+                    .size(12)
+                    .style(EOS_TEXT),
+                    text(match (self.is_running, self.midi_queue) {
+                        (true, Some(q)) => format!(
+                            "MIDI queue {} (peak {}) · {} dropped",
+                            q.depth, q.peak, q.dropped
+                        ),
+                        _ => String::new(),
+                    })
+                    .size(12)
+                    .style(
+                        if self.midi_queue.is_some_and(|q| q.dropped > 0) {
+                            EOS_AMBER
+                        } else {
+                            EOS_TEXT
+                        }
+                    )
                 ]
                 .spacing(8)
-                .align_items(Alignment::Center)
+                .align_items(Alignment::Center),
+                console_row
             ]
             .spacing(20)
             .align_items(Alignment::Center),
@@ -285,6 +694,36 @@ impl Application for EosBridge {
                     .spacing(5),
                 ]
                 .spacing(20),
+                row![
+                    column![
+                        text("Device Profile").size(12),
+                        pick_list(
+                            &DeviceProfile::ALL[..],
+                            Some(self.config.device_profile),
+                            Message::ProfileSelected
+                        )
+                        .width(300)
+                    ]
+                    .spacing(5),
+                    button(text(if self.detecting {
+                        "DETECTING..."
+                    } else {
+                        "DETECT CONTROLLER"
+                    }))
+                    .on_press_maybe(
+                        (!self.is_running && !self.detecting).then_some(Message::DetectControllers)
+                    )
+                    .padding(10)
+                ]
+                .spacing(20)
+                .align_items(Alignment::End),
+                column(self.detected_devices.iter().enumerate().map(|(i, dev)| {
+                    button(text(dev.to_string()).size(12))
+                        .on_press(Message::DetectedDeviceSelected(i))
+                        .padding(5)
+                        .into()
+                }))
+                .spacing(5),
                 button(
                     text(if self.is_running {
                         "DISCONNECT"
@@ -317,7 +756,31 @@ impl Application for EosBridge {
                     text("EOS IP:").width(Length::FillPortion(1)),
                     text_input("127.0.0.1", &self.eos_ip_value)
                         .width(Length::FillPortion(2))
-                        .on_input(Message::EosIpChanged)
+                        .on_input(Message::EosIpChanged),
+                    button(text(if self.discovering {
+                        "SEARCHING..."
+                    } else {
+                        "DISCOVER"
+                    }))
+                    .on_press_maybe((!self.discovering).then_some(Message::DiscoverConsoles))
+                ]
+                .align_items(Alignment::Center)
+                .spacing(8),
+                column(self.discovered_consoles.iter().map(|c| {
+                    button(text(c.to_string()).size(12))
+                        .on_press(Message::EosIpChanged(c.ip.clone()))
+                        .padding(5)
+                        .into()
+                }))
+                .spacing(5),
+                row![
+                    text("Backup IPs:").width(Length::FillPortion(1)),
+                    text_input(
+                        "10.101.100.102, 10.101.100.103:8000",
+                        &self.backup_ips_value
+                    )
+                    .width(Length::FillPortion(2))
+                    .on_input(Message::BackupIpsChanged)
                 ]
                 .align_items(Alignment::Center)
                 .spacing(8),
@@ -337,6 +800,36 @@ impl Application for EosBridge {
                 ]
                 .align_items(Alignment::Center)
                 .spacing(8),
+                row![
+                    text("OSC Transport:").width(Length::FillPortion(1)),
+                    pick_list(
+                        &OscTransport::ALL[..],
+                        Some(self.config.osc_transport),
+                        Message::TransportSelected
+                    )
+                    .width(Length::FillPortion(2))
+                ]
+                .align_items(Alignment::Center)
+                .spacing(8),
+                row![
+                    text("EOS User:").width(Length::FillPortion(1)),
+                    text_input("default", &self.eos_user_value)
+                        .width(Length::FillPortion(1))
+                        .on_input(Message::EosUserChanged),
+                    checkbox("Prefix commands", self.config.prefix_user_commands)
+                        .on_toggle(Message::PrefixUserToggled)
+                        .width(Length::FillPortion(1))
+                ]
+                .align_items(Alignment::Center)
+                .spacing(8),
+                checkbox(
+                    "Fader pickup (non-motorized surfaces)",
+                    self.config
+                        .mappings
+                        .iter()
+                        .any(|m| m.event_type == MidiEventType::PitchBend && m.pickup)
+                )
+                .on_toggle(Message::PickupToggled),
                 button("Save Configuration").on_press(Message::SaveConfig)
             ]
             .spacing(10),
@@ -352,6 +845,148 @@ impl Application for EosBridge {
             ..Default::default()
         });
 
+        let traffic_filter = self.traffic_filter.to_lowercase();
+        let monitor_panel = container(if self.show_monitor {
+            let lines: iced::widget::Column<'_, Message> = column(
+                self.traffic
+                    .iter()
+                    .rev()
+                    .filter(|e| {
+                        traffic_filter.is_empty()
+                            || e.address.to_lowercase().contains(&traffic_filter)
+                    })
+                    .map(|e| {
+                        row![
+                            text(&e.timestamp).size(11).width(150),
+                            text(e.direction.to_string())
+                                .size(11)
+                                .style(EOS_GOLD)
+                                .width(60),
+                            text(&e.address).size(11).width(300),
+                            text(&e.detail).size(11),
+                        ]
+                        .spacing(8)
+                        .into()
+                    }),
+            )
+            .spacing(2);
+            column![
+                row![
+                    button("Hide Monitor").on_press(Message::ToggleMonitor),
+                    button(if self.monitor_paused {
+                        "Resume"
+                    } else {
+                        "Pause"
+                    })
+                    .on_press(Message::PauseMonitor),
+                    button("Clear").on_press(Message::ClearMonitor),
+                    text_input("Address filter...", &self.traffic_filter)
+                        .on_input(Message::TrafficFilterChanged)
+                        .width(Length::Fill),
+                ]
+                .spacing(10)
+                .align_items(Alignment::Center),
+                scrollable(lines).height(220).width(Length::Fill)
+            ]
+            .spacing(10)
+        } else {
+            column![button("Show Traffic Monitor").on_press(Message::ToggleMonitor)]
+        })
+        .padding(10)
+        .width(800)
+        .style(move |_: &Theme| container::Appearance {
+            background: Some(EOS_SURFACE.into()),
+            border: iced::Border {
+                width: 1.0,
+                color: Color::BLACK,
+                radius: 4.0.into(),
+            },
+            ..Default::default()
+        });
+
+        let session_panel = container(
+            row![
+                button(if self.recording.is_some() {
+                    "■ STOP REC"
+                } else {
+                    "● REC"
+                })
+                .on_press(Message::ToggleRecording),
+                text_input("Session file (.jsonl)", &self.replay_path_value)
+                    .on_input(Message::ReplayPathChanged)
+                    .width(Length::Fill),
+                button(if self.replaying {
+                    "Replaying..."
+                } else {
+                    "Replay"
+                })
+                .on_press_maybe((!self.replaying).then_some(Message::ReplaySession)),
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center),
+        )
+        .padding(10)
+        .width(800)
+        .style(move |_: &Theme| container::Appearance {
+            background: Some(EOS_SURFACE.into()),
+            border: iced::Border {
+                width: 1.0,
+                color: Color::BLACK,
+                radius: 4.0.into(),
+            },
+            ..Default::default()
+        });
+
+        let filter = self.log_filter.to_lowercase();
+        let log_lines = column(
+            self.log_entries
+                .iter()
+                .rev()
+                .filter(|e| e.level >= self.log_min_level)
+                .filter(|e| filter.is_empty() || e.message.to_lowercase().contains(&filter))
+                .map(|e| {
+                    let color = match e.level {
+                        LogLevel::Error => EOS_RED,
+                        LogLevel::Warn => EOS_AMBER,
+                        LogLevel::Info => EOS_TEXT,
+                        LogLevel::Debug => Color::from_rgb(0.5, 0.5, 0.5),
+                    };
+                    text(e.to_string()).size(11).style(color).into()
+                }),
+        )
+        .spacing(2);
+        let log_panel = container(
+            column![
+                row![
+                    text("Log").style(EOS_GOLD),
+                    pick_list(
+                        &LogLevel::ALL[..],
+                        Some(self.log_min_level),
+                        Message::LogLevelSelected
+                    ),
+                    text_input("Filter...", &self.log_filter)
+                        .on_input(Message::LogFilterChanged)
+                        .width(Length::Fill),
+                    button("Clear").on_press(Message::ClearLog)
+                ]
+                .spacing(10)
+                .align_items(Alignment::Center),
+                scrollable(log_lines).height(180).width(Length::Fill)
+            ]
+            .spacing(10),
+        )
+        .padding(10)
+        .width(800)
+        .style(move |_: &Theme| container::Appearance {
+            background: Some(EOS_SURFACE.into()),
+            border: iced::Border {
+                width: 1.0,
+                color: Color::BLACK,
+                radius: 4.0.into(),
+            },
+            ..Default::default()
+        });
+
         let fader_bank =
             row(self
                 .fader_levels
@@ -384,6 +1019,14 @@ impl Application for EosBridge {
                         text(format!("{:.0}%", lvl * 100.0))
                             .size(12)
                             .style(EOS_GOLD),
+                        // Which way to move a pickup fader to catch the Eos level
+                        text(match self.pickup_hints[i] {
+                            Some(PickupDirection::Up) => "▲ pick up",
+                            Some(PickupDirection::Down) => "▼ pick up",
+                            None => "",
+                        })
+                        .size(11)
+                        .style(EOS_AMBER),
                     ]
                     .align_items(Alignment::Center)
                     .spacing(8)
@@ -391,11 +1034,19 @@ impl Application for EosBridge {
                 }))
             .spacing(10);
 
-        container(
-            column![header, setup_box, cfg_column, fader_bank]
-                .spacing(30)
-                .align_items(Alignment::Center),
-        )
+        container(scrollable(
+            column![
+                header,
+                setup_box,
+                cfg_column,
+                fader_bank,
+                monitor_panel,
+                session_panel,
+                log_panel
+            ]
+            .spacing(30)
+            .align_items(Alignment::Center),
+        ))
         .width(Length::Fill)
         .height(Length::Fill)
         .style(|_: &Theme| container::Appearance {
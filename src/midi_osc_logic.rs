@@ -1,25 +1,446 @@
-use crate::config::{float_to_pitch_bend, Config, MidiEventType};
+use crate::config::{
+    float_to_pitch_bend, user_address, Config, DeviceProfile, MidiEventType, MidiOscMapping,
+};
+use crate::faders::{FaderState, PickupDirection, SharedFaders};
+use crate::heartbeat::{self, LinkStats, PingTracker};
+use crate::hires_cc::{self, HiResDecoder, HiResInput};
+use crate::hui::{self, HuiDecoder, HuiEvent};
+use crate::layers::{LayerState, SharedLayers};
+use crate::logging::{self, LogEntry, LogLevel, Logger};
+use crate::monitor::{Direction, Monitor, TrafficEntry};
+use crate::osc_transport::{self, OscInput, OscSender};
+use crate::routing::RoutingTable;
+use crate::sequence::{Sequencer, SharedSequencer};
+use crate::session::SessionRecorder;
+use crate::template::{self, TemplateVars};
+use crate::throttle::{OscThrottle, SharedThrottle};
 use deunicode::deunicode;
 use iced::futures::SinkExt;
 use midir::{MidiInput, MidiOutput, MidiOutputConnection};
-use rosc::{decoder, encoder, OscMessage, OscPacket, OscType};
+use rosc::{OscMessage, OscPacket, OscType};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
-use tokio::net::UdpSocket;
-use tokio::time::{sleep, Duration};
+use tokio::time::{Duration, Instant};
+
+/// MIDI messages buffered between the midir callback and the handler task
+const MIDI_QUEUE_CAPACITY: usize = 1024;
+/// How often held back motor moves and OSC values are flushed
+pub(crate) const ENGINE_TICK: Duration = Duration::from_millis(5);
+/// A console that hasn't answered a ping for this long is considered down
+const CONSOLE_TIMEOUT: Duration = Duration::from_secs(11);
 
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
 pub enum BridgeEvent {
     None,
-    Log(String),
+    Log(LogEntry),
     FaderUpdate(u8, f32),
     LabelUpdate(u8, String),
-    MidiCaptured(MidiEventType, u8, [u8; 3]),
+    MidiCaptured(MidiEventType, u16, [u8; 3]),
     ConnectionHeartbeat,
+    /// Round trip figures for the active console, refreshed on every ping reply
+    LinkStats(LinkStats),
+    /// Which configured consoles are answering, primary first
+    ConsoleStatus(Vec<bool>),
+    /// Index of the console now driving output and feedback
+    ActiveConsole(usize),
+    /// One message for the traffic monitor
+    Traffic(TrafficEntry),
+    /// Pickup fader waiting to reach the Eos level, `None` once it has control
+    Pickup(u8, Option<PickupDirection>),
+    /// MIDI input backlog, sent every second
+    MidiQueue(MidiQueueStats),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MidiQueueStats {
+    /// Messages waiting when last looked at
+    pub depth: usize,
+    /// Deepest the queue got since the previous report
+    pub peak: usize,
+    /// Messages lost to a full queue since the bridge started
+    pub dropped: u64,
+}
+
+/// Filled in by the midir callback and the MIDI handler task
+#[derive(Debug, Default)]
+struct QueueCounters {
+    depth: AtomicUsize,
+    peak: AtomicUsize,
+    dropped: AtomicU64,
+}
+
+impl QueueCounters {
+    fn snapshot(&self) -> MidiQueueStats {
+        MidiQueueStats {
+            depth: self.depth.load(Ordering::Relaxed),
+            peak: self.peak.swap(0, Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// State the GUI shares with a running bridge
+#[derive(Clone, Default)]
+pub struct BridgeShared {
+    /// Console selected for output, written by the UI for manual switches
+    pub active_console: Arc<AtomicUsize>,
+    /// Whether the traffic monitor wants entries
+    pub monitor_enabled: Arc<AtomicBool>,
+    pub recorder: SessionRecorder,
+}
+
+/// MIDI output to the surface that mirrors everything it sends to the traffic monitor
+pub(crate) struct SurfaceOut {
+    sink: SurfaceSink,
+    monitor: Monitor,
+}
+
+enum SurfaceSink {
+    Midi(MidiOutputConnection),
+    /// Collects what would have been sent, for session replay
+    Capture(Vec<Vec<u8>>),
+}
+
+impl SurfaceOut {
+    pub(crate) fn capture(monitor: Monitor) -> Self {
+        SurfaceOut {
+            sink: SurfaceSink::Capture(vec![]),
+            monitor,
+        }
+    }
+
+    /// Drains the messages collected by a capture sink
+    pub(crate) fn take_captured(&mut self) -> Vec<Vec<u8>> {
+        match &mut self.sink {
+            SurfaceSink::Capture(sent) => std::mem::take(sent),
+            SurfaceSink::Midi(_) => vec![],
+        }
+    }
+
+    fn send(&mut self, bytes: &[u8]) -> Result<(), midir::SendError> {
+        self.monitor.midi(Direction::MidiOut, bytes);
+        match &mut self.sink {
+            SurfaceSink::Midi(conn) => conn.send(bytes),
+            SurfaceSink::Capture(sent) => {
+                sent.push(bytes.to_vec());
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Output the MIDI handler leaves for `engine_tick`
+#[derive(Clone)]
+pub(crate) struct EngineQueues {
+    /// Continuous values held back for coalescing
+    throttle: SharedThrottle,
+    /// Delayed action steps
+    sequencer: SharedSequencer,
+    /// Active layer and its key LEDs
+    layers: SharedLayers,
+}
+
+impl EngineQueues {
+    fn new(cfg: &Config) -> Self {
+        EngineQueues {
+            throttle: OscThrottle::shared(cfg.max_osc_rate_hz),
+            sequencer: Sequencer::shared(),
+            layers: LayerState::shared(),
+        }
+    }
+}
+
+/// Turns raw surface MIDI into OSC for Eos. Runs on the MIDI handler task, or in a replay.
+pub(crate) struct MidiInputHandler {
+    cfg: Arc<Config>,
+    tx: OscSender,
+    faders: SharedFaders,
+    queues: EngineQueues,
+    routes: Arc<RoutingTable>,
+    ui: iced::futures::channel::mpsc::Sender<BridgeEvent>,
+    hui_decoder: HuiDecoder,
+    hires_decoder: HiResDecoder,
+    log: Logger,
+}
+
+impl MidiInputHandler {
+    pub(crate) fn new(
+        cfg: Arc<Config>,
+        tx: OscSender,
+        faders: SharedFaders,
+        ui: iced::futures::channel::mpsc::Sender<BridgeEvent>,
+        log: Logger,
+    ) -> Self {
+        MidiInputHandler {
+            hires_decoder: HiResDecoder::from_config(&cfg),
+            hui_decoder: HuiDecoder::default(),
+            queues: EngineQueues::new(&cfg),
+            routes: Arc::new(RoutingTable::new(&cfg)),
+            cfg,
+            tx,
+            faders,
+            ui,
+            log,
+        }
+    }
+
+    /// Sends whatever the mapping asks for
+    pub(crate) fn handle(&mut self, msg: &[u8]) {
+        if msg.len() < 3 {
+            return;
+        }
+        let decoded = match self.cfg.device_profile {
+            DeviceProfile::Hui => decode_hui(msg, &mut self.hui_decoder, &self.faders),
+            _ => match self.hires_decoder.feed(msg) {
+                HiResInput::Value(t, n, v) => Some((t, n, v)),
+                HiResInput::Pending => None,
+                HiResInput::Passthrough => decode_mcu(msg, &self.faders),
+            },
+        };
+        let Some((etype, dnum, value)) = decoded else {
+            return;
+        };
+        if let Some(key) = self.routes.layer_key(&etype, dnum) {
+            let latch = self.cfg.layers[key].latch;
+            if let Ok(mut layers) = self.queues.layers.lock() {
+                layers.key(key, latch, value > 0.0);
+            }
+            return;
+        }
+        if etype == MidiEventType::HuiSwitch && value == 0.0 {
+            // HUI releases only matter to layer keys
+            return;
+        }
+        // Optional: Send event to UI for monitoring
+        let _ = self.ui.try_send(BridgeEvent::MidiCaptured(
+            etype.clone(),
+            dnum,
+            [msg[0], msg[1], msg[2]],
+        ));
+
+        let vars = TemplateVars {
+            channel: msg[0] & 0x0F,
+            number: dnum,
+            value,
+            page: self.faders.lock().map_or(1, |f| f.page()),
+        };
+        let cfg = self.cfg.clone();
+        let routes = self.routes.clone();
+        let matching = routes.midi(&etype, dnum, vars.channel);
+        let active = self
+            .queues
+            .layers
+            .lock()
+            .ok()
+            .and_then(|l| l.active())
+            .map(|i| cfg.layers[i].name.as_str());
+        // The active layer overrides the base mappings of a control it has mappings for
+        let layer = active.filter(|name| {
+            matching
+                .clone()
+                .any(|i| cfg.mappings[i].layer.as_deref() == Some(*name))
+        });
+        // Every mapping on this event fires, in config order
+        for i in matching {
+            let m = &cfg.mappings[i];
+            if m.layer.as_deref() == layer {
+                self.fire(m, &etype, dnum, value, &vars);
+            }
+        }
+    }
+
+    /// Sends what one mapping asks for
+    fn fire(
+        &mut self,
+        m: &MidiOscMapping,
+        etype: &MidiEventType,
+        dnum: u16,
+        value: f32,
+        vars: &TemplateVars,
+    ) {
+        // Running a command or sequence again on release would do it twice. A CC at 0
+        // is a button release too; only fader events fire at every value
+        let pressed = value > 0.0
+            || matches!(
+                etype,
+                MidiEventType::PitchBend | MidiEventType::ControlChange14 | MidiEventType::Nrpn
+            );
+        if let Some(cmd) = &m.command {
+            if pressed {
+                let mut msg = template::render_command(cmd, vars);
+                msg.addr = user_address(&self.cfg, &msg.addr);
+                self.tx.send(&OscPacket::Message(msg));
+            }
+            return;
+        }
+        if !m.steps.is_empty() {
+            if pressed {
+                self.run_steps(m, vars);
+            }
+            return;
+        }
+        let osc_address = template::render(&m.osc_address, vars);
+        // A ranged mapping drives whichever fader the event came from
+        let idx = match m.data_number_end {
+            Some(_) => dnum as u8,
+            None => m.data_number,
+        };
+        let mut args = vec![];
+        // A CC with a fixed value is a button, not a fader
+        let button = matches!(etype, MidiEventType::NoteOn | MidiEventType::HuiSwitch)
+            || (*etype == MidiEventType::ControlChange && m.fixed_osc_value.is_some());
+        if button {
+            // Sending on release too would press the key twice
+            if !pressed {
+                return;
+            }
+            if let Some(v) = m.fixed_osc_value {
+                args.push(OscType::Float(v));
+            }
+        } else {
+            if m.pickup && !self.pick_up(idx, &osc_address, value) {
+                return;
+            }
+            if *etype == MidiEventType::PitchBend {
+                if let Ok(mut faders) = self.faders.lock() {
+                    let now = faders.now();
+                    faders.note_sent(idx, value, now);
+                }
+            }
+            args.push(OscType::Float(value));
+        }
+        if !m.osc_args.is_empty() {
+            match template::render_args(&m.osc_args, vars) {
+                Ok(rendered) => args = rendered,
+                Err(e) => {
+                    self.log.warn(format!(
+                        "{:?} {} mapping to {} not sent: {}",
+                        m.event_type, m.data_number, m.osc_address, e
+                    ));
+                    return;
+                }
+            }
+        }
+        let Some((dest, addr)) = self.address_for(m.destination.as_deref(), &osc_address) else {
+            return;
+        };
+        // A release is no second page turn
+        if dest.is_none() && pressed {
+            if let Ok(mut faders) = self.faders.lock() {
+                faders.track_page(&osc_address);
+            }
+        }
+        let packet = (
+            dest,
+            OscPacket::Message(OscMessage {
+                addr: addr.clone(),
+                args,
+            }),
+        );
+        if button {
+            // Buttons never wait
+            self.tx.deliver(dest, &packet.1);
+            return;
+        }
+        let now = match self.faders.lock() {
+            Ok(faders) => faders.now(),
+            Err(_) => std::time::Instant::now(),
+        };
+        let ready = match self.queues.throttle.lock() {
+            Ok(mut throttle) => throttle.offer(&addr, packet, now),
+            Err(_) => Some(packet),
+        };
+        if let Some((dest, packet)) = ready {
+            self.tx.deliver(dest, &packet);
+        }
+    }
+
+    /// Destination and final address: Eos addresses get the user prefix.
+    /// `None` if `destination` names nothing in the config
+    fn address_for(
+        &self,
+        destination: Option<&str>,
+        addr: &str,
+    ) -> Option<(Option<usize>, String)> {
+        match destination {
+            None => Some((None, user_address(&self.cfg, addr))),
+            Some(name) => Some((Some(self.cfg.destination_index(name)?), addr.to_string())),
+        }
+    }
+
+    /// Mapping lookup shared with the feedback side
+    pub(crate) fn routes(&self) -> Arc<RoutingTable> {
+        self.routes.clone()
+    }
+
+    /// Held back output, flushed by `engine_tick`
+    pub(crate) fn queues(&self) -> EngineQueues {
+        self.queues.clone()
+    }
+
+    /// Sends the steps up to the first delay now and schedules the rest
+    fn run_steps(&self, m: &MidiOscMapping, vars: &TemplateVars) {
+        let Ok(mut sequencer) = self.queues.sequencer.lock() else {
+            return;
+        };
+        let now = self
+            .faders
+            .lock()
+            .map_or_else(|_| std::time::Instant::now(), |f| f.now());
+        let mut at = now;
+        for step in &m.steps {
+            at += std::time::Duration::from_millis(step.delay_ms);
+            let mut msg = match template::render_step(step, vars) {
+                Ok(msg) => msg,
+                Err(e) => {
+                    self.log.warn(format!(
+                        "{:?} {} step {} not sent: {}",
+                        m.event_type, m.data_number, step.osc_address, e
+                    ));
+                    continue;
+                }
+            };
+            // Command lines only make sense on Eos
+            let destination = match step.command {
+                Some(_) => None,
+                None => step.destination.as_deref().or(m.destination.as_deref()),
+            };
+            let Some((dest, addr)) = self.address_for(destination, &msg.addr) else {
+                continue;
+            };
+            msg.addr = addr;
+            let packet = OscPacket::Message(msg);
+            if at == now {
+                self.tx.deliver(dest, &packet);
+            } else {
+                sequencer.schedule(at, (dest, packet));
+            }
+        }
+    }
+
+    /// Soft takeover for a physical move; tells the UI when the direction hint changes
+    fn pick_up(&mut self, idx: u8, addr: &str, value: f32) -> bool {
+        let Ok(mut faders) = self.faders.lock() else {
+            return true;
+        };
+        let before = faders.pickup_direction(addr);
+        let now = faders.now();
+        let engaged = faders.pickup_input(addr, value, self.cfg.pickup_tolerance, now);
+        let after = faders.pickup_direction(addr);
+        if before != after {
+            let _ = self.ui.try_send(BridgeEvent::Pickup(idx, after));
+        }
+        engaged
+    }
 }
 
 /// Sends MCU Sysex commands to update the iCon D2 LCD scribble strips
-fn send_mcu_label(conn: &mut MidiOutputConnection, fader_idx: u8, label: &str) {
+fn send_mcu_label(
+    conn: &mut SurfaceOut,
+    fader_idx: u8,
+    label: &str,
+) -> Result<(), midir::SendError> {
     // MCU Sysex Header for iCon/Mackie Display
     let mut sysex = vec![0xF0, 0x00, 0x00, 0x66, 0x14, 0x12];
 
@@ -34,10 +455,10 @@ fn send_mcu_label(conn: &mut MidiOutputConnection, fader_idx: u8, label: &str) {
 
     sysex.extend_from_slice(truncated);
     sysex.push(0xF7);
-    let _ = conn.send(&sysex);
+    conn.send(&sysex)
 }
 
-pub fn clear_mcu_display(conn: &mut midir::MidiOutputConnection) {
+pub fn clear_mcu_display(conn: &mut midir::MidiOutputConnection) -> Result<(), midir::SendError> {
     // Standard Mackie LCD Header (0x12 = LCD command)
     let mut sysex = vec![0xF0, 0x00, 0x00, 0x66, 0x14, 0x12];
     sysex.push(0x00); // Start at the first character
@@ -47,138 +468,287 @@ pub fn clear_mcu_display(conn: &mut midir::MidiOutputConnection) {
     sysex.extend_from_slice(spaces.as_bytes());
 
     sysex.push(0xF7); // End of Sysex
-    let _ = conn.send(&sysex);
+    conn.send(&sysex)
+}
+
+/// Blanks every scribble strip using whichever protocol the surface speaks
+pub fn clear_display(
+    conn: &mut MidiOutputConnection,
+    profile: DeviceProfile,
+) -> Result<(), midir::SendError> {
+    match profile {
+        DeviceProfile::Hui => (0..8).try_for_each(|strip| conn.send(&hui::label_sysex(strip, ""))),
+        _ => clear_mcu_display(conn),
+    }
+}
+
+/// Sends a scribble strip label for fader 1-8
+fn send_label(
+    conn: &mut SurfaceOut,
+    profile: DeviceProfile,
+    fader_idx: u8,
+    label: &str,
+) -> Result<(), midir::SendError> {
+    match profile {
+        DeviceProfile::Hui => conn.send(&hui::label_sysex(fader_idx - 1, label)),
+        _ => send_mcu_label(conn, fader_idx, label),
+    }
+}
+
+/// Drives the motor of fader 1-8 to a normalized level
+fn send_fader(
+    conn: &mut SurfaceOut,
+    profile: DeviceProfile,
+    fader_idx: u8,
+    value: f32,
+) -> Result<(), midir::SendError> {
+    let pb = float_to_pitch_bend(value);
+    match profile {
+        DeviceProfile::Hui => {
+            for m in hui::fader_message(fader_idx - 1, pb) {
+                conn.send(&m)?;
+            }
+            Ok(())
+        }
+        _ => conn.send(&[0xE0 | (fader_idx - 1), (pb & 0x7F) as u8, (pb >> 7) as u8]),
+    }
+}
+
+/// Button LED on or off; MCU echoes the button's note, HUI addresses its zone and port
+fn send_led(
+    conn: &mut SurfaceOut,
+    profile: DeviceProfile,
+    button: u8,
+    on: bool,
+) -> Result<(), midir::SendError> {
+    match profile {
+        DeviceProfile::Hui => {
+            for m in hui::led_message(button, on) {
+                conn.send(&m)?;
+            }
+            Ok(())
+        }
+        _ => conn.send(&[0x90, button, if on { 0x7F } else { 0x00 }]),
+    }
+}
+
+/// Decodes MCU input into (event type, data number, normalized value), tracking touches
+fn decode_mcu(
+    msg: &[u8],
+    faders: &std::sync::Mutex<FaderState>,
+) -> Option<(MidiEventType, u16, f32)> {
+    let status = msg[0] & 0xF0;
+
+    // Handle Fader Touch for Motor Safety
+    if status == 0x90 || status == 0x80 {
+        let note = msg[1];
+        let is_touch = status == 0x90 && msg[2] > 0;
+        if let Ok(mut faders) = faders.lock() {
+            let now = faders.now();
+            // Notes 104-111 are fader touches on Platform M+
+            if (104..=111).contains(&note) {
+                faders.set_touched(note - 103, is_touch, now);
+            } else if note == 112 {
+                faders.set_touched(9, is_touch, now);
+            }
+        }
+    }
+
+    match status {
+        0xE0 => Some((
+            MidiEventType::PitchBend,
+            ((msg[0] & 0x0F) + 1) as u16,
+            ((msg[2] as u16) * 128 + (msg[1] as u16)) as f32 / 16383.0,
+        )),
+        0x90 => Some((MidiEventType::NoteOn, msg[1] as u16, msg[2] as f32 / 127.0)),
+        // Note Off is a release, the same as Note On at velocity 0
+        0x80 => Some((MidiEventType::NoteOn, msg[1] as u16, 0.0)),
+        0xB0 => Some((
+            MidiEventType::ControlChange,
+            msg[1] as u16,
+            msg[2] as f32 / 127.0,
+        )),
+        _ => None,
+    }
+}
+
+/// Decodes HUI input the same way; faders show up as PitchBend so mappings carry over
+fn decode_hui(
+    msg: &[u8],
+    decoder: &mut HuiDecoder,
+    faders: &std::sync::Mutex<FaderState>,
+) -> Option<(MidiEventType, u16, f32)> {
+    match decoder.feed(msg)? {
+        HuiEvent::Touch(strip, is_touch) => {
+            if let Ok(mut faders) = faders.lock() {
+                let now = faders.now();
+                faders.set_touched(strip + 1, is_touch, now);
+            }
+            None
+        }
+        HuiEvent::Fader(strip, v) => Some((
+            MidiEventType::PitchBend,
+            (strip + 1) as u16,
+            v as f32 / 16383.0,
+        )),
+        HuiEvent::Switch {
+            zone,
+            port,
+            pressed: true,
+        } => Some((
+            MidiEventType::HuiSwitch,
+            hui::switch_number(zone, port) as u16,
+            1.0,
+        )),
+        HuiEvent::Switch { zone, port, .. } => Some((
+            MidiEventType::HuiSwitch,
+            hui::switch_number(zone, port) as u16,
+            0.0,
+        )),
+    }
+}
+
+/// Selects our Eos user and requests the fader config so labels and levels come in
+fn sync_console(tx: &OscSender, target: usize, eos_user: Option<u16>) {
+    // Work as the configured user so we don't collide with the main desk
+    if let Some(user) = eos_user {
+        tx.send_to(
+            target,
+            &OscPacket::Message(OscMessage {
+                addr: "/eos/user".into(),
+                args: vec![OscType::Int(user as i32)],
+            }),
+        );
+    }
+    let init_msg = OscMessage {
+        addr: "/eos/fader/1/config/10".into(),
+        args: vec![],
+    };
+    tx.send_to(target, &OscPacket::Message(init_msg));
 }
 
 pub fn bridge_subscription(
     in_name: String,
     out_name: String,
     cfg: Arc<Config>,
+    shared: BridgeShared,
 ) -> iced::Subscription<BridgeEvent> {
     iced::subscription::channel(
         std::any::TypeId::of::<()>(),
         100,
         move |mut output| async move {
+            let (log, mut log_rx) = Logger::new();
+            let (monitor, mut monitor_rx) = Monitor::new(shared.monitor_enabled.clone());
+            let active_console = shared.active_console.clone();
             let midi_in = MidiInput::new("Eos-Bridge-In").unwrap();
             let midi_out = MidiOutput::new("Eos-Bridge-Out").unwrap();
 
             let in_p = midi_in
                 .ports()
                 .into_iter()
-                .find(|p| midi_in.port_name(p).unwrap_or_default() == in_name)
-                .expect("MIDI In Port Missing");
+                .find(|p| midi_in.port_name(p).unwrap_or_default() == in_name);
             let out_p = midi_out
                 .ports()
                 .into_iter()
-                .find(|p| midi_out.port_name(p).unwrap_or_default() == out_name)
-                .expect("MIDI Out Port Missing");
-
-            let eos_addr = format!("{}:{}", cfg.eos_ip, cfg.eos_port);
-            let send_socket = std::net::UdpSocket::bind("0.0.0.0:0").unwrap();
-            let recv_socket = UdpSocket::bind(format!("0.0.0.0:{}", cfg.listen_port))
-                .await
-                .unwrap();
-
-            // --- Sync Task: Request current fader config from Eos ---
-            let hb_socket = send_socket.try_clone().unwrap();
-            let hb_addr = eos_addr.clone();
-            tokio::spawn(async move {
-                // Initial sync
-                let init_msg = OscMessage {
-                    addr: "/eos/fader/1/config/10".into(),
-                    args: vec![],
-                };
-                if let Ok(buf) = encoder::encode(&OscPacket::Message(init_msg)) {
-                    let _ = hb_socket.send_to(&buf, &hb_addr);
-                }
+                .find(|p| midi_out.port_name(p).unwrap_or_default() == out_name);
+            let (Some(in_p), Some(out_p)) = (in_p, out_p) else {
+                let err = logging::entry(LogLevel::Error, "MIDI port missing, bridge not started");
+                let _ = output.send(BridgeEvent::Log(err)).await;
+                return std::future::pending().await;
+            };
 
-                loop {
-                    // Ping every 5 seconds to keep the UI "Green"
-                    sleep(Duration::from_secs(5)).await;
-                    let ping = OscMessage {
-                        addr: "/eos/ping".into(),
-                        args: vec![OscType::String("BridgeSync".into())],
-                    };
-                    if let Ok(buf) = encoder::encode(&OscPacket::Message(ping)) {
-                        let _ = hb_socket.send_to(&buf, &hb_addr);
-                    }
+            let (osc_tx, mut osc_rx) = match osc_transport::open(
+                &cfg,
+                active_console.clone(),
+                log.clone(),
+                monitor.clone(),
+            )
+            .await
+            {
+                Ok(t) => t,
+                Err(e) => {
+                    let err = logging::entry(
+                        LogLevel::Error,
+                        format!("Could not open OSC transport: {}", e),
+                    );
+                    let _ = output.send(BridgeEvent::Log(err)).await;
+                    return std::future::pending().await;
                 }
+            };
+            log.info(format!(
+                "Bridge started: MIDI in \"{}\", out \"{}\", {} to {}:{}",
+                in_name, out_name, cfg.osc_transport, cfg.eos_ip, cfg.eos_port
+            ));
+            for d in &cfg.destinations {
+                log.info(format!("OSC destination \"{}\" at {}", d.name, d.addr()));
+            }
+            let named = cfg.mappings.iter().flat_map(|m| {
+                let steps = m.steps.iter().map(|s| s.destination.as_deref());
+                std::iter::once(m.destination.as_deref()).chain(steps)
             });
+            for name in named.flatten().collect::<std::collections::BTreeSet<_>>() {
+                if cfg.destination_index(name).is_none() {
+                    log.warn(format!(
+                        "Unknown OSC destination \"{}\", its mappings won't send",
+                        name
+                    ));
+                }
+            }
+            let layers = cfg.mappings.iter().filter_map(|m| m.layer.as_deref());
+            for name in layers.collect::<std::collections::BTreeSet<_>>() {
+                if !cfg.layers.iter().any(|l| l.name == name) {
+                    log.warn(format!(
+                        "Unknown layer \"{}\", its mappings won't fire",
+                        name
+                    ));
+                }
+            }
+
+            // --- Initial Sync: Request current fader config from Eos ---
+            let mut current_console = active_console.load(Ordering::Relaxed);
+            sync_console(&osc_tx, current_console, cfg.eos_user);
 
             // --- MIDI Input to OSC Out ---
-            let touched_faders = Arc::new(std::sync::Mutex::new([false; 13]));
-            let touched_faders_cb = touched_faders.clone();
-            let mut midi_tx = output.clone();
-            let tx_sock = send_socket.try_clone().unwrap();
-            let tx_addr = eos_addr.clone();
-            let cfg_midi = cfg.clone();
+            let faders = FaderState::shared();
+            let mut handler = MidiInputHandler::new(
+                cfg.clone(),
+                osc_tx.clone(),
+                faders.clone(),
+                output.clone(),
+                log.clone(),
+            );
+            let queues = handler.queues();
+            let routes = handler.routes();
+            let monitor_cb = monitor.clone();
+            let recorder = shared.recorder.clone();
+            // The midir callback only queues; recording, mapping and network I/O happen
+            // on a tokio task. Messages carry their arrival time for the recording
+            let (midi_queue, mut midi_rx) =
+                tokio::sync::mpsc::channel::<(std::time::Instant, Vec<u8>)>(MIDI_QUEUE_CAPACITY);
+            let queue = Arc::new(QueueCounters::default());
+            let dropped_cb = queue.clone();
+            let queue_task = queue.clone();
+            tokio::spawn(async move {
+                // Ends when the midir connection, and with it the sender, is dropped
+                while let Some((at, msg)) = midi_rx.recv().await {
+                    let waiting = midi_rx.len();
+                    queue_task.depth.store(waiting, Ordering::Relaxed);
+                    queue_task.peak.fetch_max(waiting + 1, Ordering::Relaxed);
+                    recorder.midi_in(&msg, at);
+                    handler.handle(&msg);
+                }
+            });
+            let mut dropped_reported = 0;
 
             let _conn_in = midi_in
                 .connect(
                     &in_p,
                     "read",
                     move |_, msg, _| {
-                        if msg.len() < 3 {
-                            return;
-                        }
-                        let status = msg[0] & 0xF0;
-
-                        // Handle Fader Touch for Motor Safety
-                        if status == 0x90 || status == 0x80 {
-                            let note = msg[1];
-                            let is_touch = status == 0x90 && msg[2] > 0;
-                            if let Ok(mut touched) = touched_faders_cb.lock() {
-                                // Notes 104-111 are fader touches on Platform M+
-                                if note >= 104 && note <= 111 {
-                                    touched[(note - 103) as usize] = is_touch;
-                                } else if note == 112 {
-                                    touched[9] = is_touch;
-                                }
-                            }
-                        }
-
-                        let (etype, dnum) = match status {
-                            0xE0 => (MidiEventType::PitchBend, (msg[0] & 0x0F) + 1),
-                            0x90 => (MidiEventType::NoteOn, msg[1]),
-                            0xB0 => (MidiEventType::ControlChange, msg[1]),
-                            _ => return,
-                        };
-
-                        // Optional: Send event to UI for monitoring
-                        let _ = midi_tx.try_send(BridgeEvent::MidiCaptured(
-                            etype.clone(),
-                            dnum,
-                            [msg[0], msg[1], msg[2]],
-                        ));
-
-                        if let Some(m) = cfg_midi
-                            .mappings
-                            .iter()
-                            .find(|map| map.event_type == etype && map.data_number == dnum)
-                        {
-                            let mut args = vec![];
-                            match etype {
-                                MidiEventType::PitchBend => {
-                                    let val =
-                                        ((msg[2] as u16) * 128 + (msg[1] as u16)) as f32 / 16383.0;
-                                    args.push(OscType::Float(val));
-                                }
-                                MidiEventType::ControlChange => {
-                                    args.push(OscType::Float(msg[2] as f32 / 127.0))
-                                }
-                                MidiEventType::NoteOn => {
-                                    if let Some(v) = m.fixed_osc_value {
-                                        args.push(OscType::Float(v));
-                                    }
-                                }
-                            }
-                            let p = OscPacket::Message(OscMessage {
-                                addr: m.osc_address.clone(),
-                                args,
-                            });
-                            if let Ok(b) = encoder::encode(&p) {
-                                let _ = tx_sock.send_to(&b, &tx_addr);
-                            }
+                        monitor_cb.midi(Direction::MidiIn, msg);
+                        let at = std::time::Instant::now();
+                        if midi_queue.try_send((at, msg.to_vec())).is_err() {
+                            dropped_cb.dropped.fetch_add(1, Ordering::Relaxed);
                         }
                     },
                     (),
@@ -186,16 +756,143 @@ pub fn bridge_subscription(
                 .unwrap();
 
             // --- OSC Rx Loop (Eos Feedback) ---
-            let mut out_conn = midi_out.connect(&out_p, "write").unwrap();
-            let mut buf = [0u8; 4096];
+            let mut out_conn = SurfaceOut {
+                sink: SurfaceSink::Midi(midi_out.connect(&out_p, "write").unwrap()),
+                monitor: monitor.clone(),
+            };
+            // HUI surfaces drop offline without a regular host ping
+            let mut hui_ping = tokio::time::interval(Duration::from_millis(hui::PING_INTERVAL_MS));
+            let mut hui_ping_failing = false;
+            // Heartbeat: ping every console and time the replies
+            let mut ping_tick = tokio::time::interval(heartbeat::PING_INTERVAL);
+            let mut pings: Vec<PingTracker> = (0..osc_tx.target_count())
+                .map(|_| PingTracker::default())
+                .collect();
+            // Failover: track when each console last answered a ping
+            let started = Instant::now();
+            let mut last_reply: Vec<Option<Instant>> = vec![None; osc_tx.target_count()];
+            let mut failover_check = tokio::time::interval(Duration::from_secs(1));
+            // Motor moves and OSC values held back by rate limiting
+            let mut tick = tokio::time::interval(ENGINE_TICK);
+            tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
             loop {
-                if let Ok((len, _)) = recv_socket.recv_from(&mut buf).await {
-                    let _ = output.send(BridgeEvent::ConnectionHeartbeat).await;
+                tokio::select! {
+                    res = osc_rx.recv() => match res {
+                        // Only the console in charge gets the session setup, a backup gets
+                        // it when it takes over
+                        Some(OscInput::Connected(target)) if target == current_console => {
+                            sync_console(&osc_tx, target, cfg.eos_user);
+                        }
+                        Some(OscInput::Packet(from, packet)) => {
+                            // Packets from an unknown source are credited to the active console
+                            let src = from.unwrap_or(current_console);
+                            osc_transport::for_each_message(&packet, &mut |m| {
+                                monitor.osc(Direction::OscIn, m)
+                            });
+                            let mut answered = false;
+                            if let Some(tracker) = pings.get_mut(src) {
+                                osc_transport::for_each_message(&packet, &mut |m| {
+                                    answered |= tracker.handle_reply(m, Instant::now());
+                                });
+                            }
+                            if answered {
+                                last_reply[src] = Some(Instant::now());
+                                if src == current_console {
+                                    let _ = output.send(BridgeEvent::ConnectionHeartbeat).await;
+                                    if let Some(stats) = pings[src].stats() {
+                                        let _ = output.send(BridgeEvent::LinkStats(stats)).await;
+                                    }
+                                }
+                            }
+                            // Only the console in charge drives motors and labels
+                            if src == current_console {
+                                shared.recorder.osc_in(&packet);
+                                process_packet(
+                                    packet,
+                                    &mut out_conn,
+                                    &mut output,
+                                    &cfg,
+                                    &routes,
+                                    &faders,
+                                    &log,
+                                )
+                                .await;
+                            }
+                        }
+                        _ => {}
+                    },
+                    _ = tick.tick() => {
+                        engine_tick(&mut out_conn, &osc_tx, &queues, &cfg, &faders, &log);
+                    }
+                    _ = ping_tick.tick() => {
+                        for (target, tracker) in pings.iter_mut().enumerate() {
+                            osc_tx.send_to(target, &tracker.next_ping(Instant::now()));
+                        }
+                        // Pings that just expired must show up as loss even if Eos went quiet
+                        if let Some(stats) = pings.get(current_console).and_then(|p| p.stats()) {
+                            let _ = output.send(BridgeEvent::LinkStats(stats)).await;
+                        }
+                    }
+                    _ = failover_check.tick() => {
+                        let alive: Vec<bool> = last_reply
+                            .iter()
+                            .map(|t| t.is_some_and(|t| t.elapsed() < CONSOLE_TIMEOUT))
+                            .collect();
+                        let requested = active_console.load(Ordering::Relaxed);
+                        // Give the primary a full timeout to show up before failing over
+                        let active_ok = alive.get(current_console).copied().unwrap_or(false)
+                            || started.elapsed() < CONSOLE_TIMEOUT;
+                        let next = if requested != current_console {
+                            // Manual switch from the UI
+                            Some(requested)
+                        } else if !active_ok {
+                            alive.iter().position(|a| *a)
+                        } else {
+                            None
+                        };
+                        if let Some(next) = next.filter(|n| *n != current_console) {
+                            if requested == current_console {
+                                log.warn(format!(
+                                    "Console {} stopped answering, failing over to console {}",
+                                    current_console + 1,
+                                    next + 1
+                                ));
+                            } else {
+                                log.info(format!("Switched to console {}", next + 1));
+                            }
+                            current_console = next;
+                            active_console.store(next, Ordering::Relaxed);
+                            sync_console(&osc_tx, next, cfg.eos_user);
+                            let _ = output.send(BridgeEvent::ActiveConsole(next)).await;
+                        }
+                        let _ = output.send(BridgeEvent::ConsoleStatus(alive)).await;
 
-                    // decode_udp is the standard for network-received OSC
-                    if let Ok((_, packet)) = decoder::decode_udp(&buf[..len]) {
-                        process_packet(packet, &mut out_conn, &mut output, &cfg, &touched_faders)
-                            .await;
+                        let stats = queue.snapshot();
+                        if stats.dropped > dropped_reported {
+                            log.warn(format!(
+                                "MIDI input queue full, dropped {} message(s)",
+                                stats.dropped - dropped_reported
+                            ));
+                            dropped_reported = stats.dropped;
+                        }
+                        let _ = output.send(BridgeEvent::MidiQueue(stats)).await;
+                    }
+                    Some(entry) = log_rx.recv() => {
+                        let _ = output.send(BridgeEvent::Log(entry)).await;
+                    }
+                    Some(entry) = monitor_rx.recv() => {
+                        let _ = output.send(BridgeEvent::Traffic(entry)).await;
+                    }
+                    _ = hui_ping.tick(), if cfg.device_profile == DeviceProfile::Hui => {
+                        // Log the first failure only, the ping repeats every second
+                        match out_conn.send(&hui::PING) {
+                            Ok(()) => hui_ping_failing = false,
+                            Err(e) if !hui_ping_failing => {
+                                log.warn(format!("HUI ping send failed: {}", e));
+                                hui_ping_failing = true;
+                            }
+                            Err(_) => {}
+                        }
                     }
                 }
             }
@@ -203,80 +900,252 @@ pub fn bridge_subscription(
     )
 }
 
+/// Sends the motor moves, OSC values and action steps that are now due, and layer key LEDs
+pub(crate) fn engine_tick(
+    midi_out: &mut SurfaceOut,
+    osc_tx: &OscSender,
+    queues: &EngineQueues,
+    cfg: &Config,
+    faders: &SharedFaders,
+    log: &Logger,
+) {
+    let (now, motors) = match faders.lock() {
+        Ok(mut faders) => {
+            let now = faders.now();
+            (now, faders.motor_pending(now))
+        }
+        Err(_) => return,
+    };
+    for (idx, pos) in motors {
+        if let Err(e) = send_fader(midi_out, cfg.device_profile, idx, pos) {
+            log.error(format!("Motor fader send failed: {}", e));
+        }
+    }
+    let leds = queues
+        .layers
+        .lock()
+        .map(|mut l| l.take_leds())
+        .unwrap_or_default();
+    for (key, on) in leds {
+        let key = &cfg.layers[key];
+        if !key.led {
+            continue;
+        }
+        if let Err(e) = send_led(midi_out, cfg.device_profile, key.data_number, on) {
+            log.error(format!("Layer LED send failed: {}", e));
+        }
+    }
+    let held = queues
+        .throttle
+        .lock()
+        .map(|mut t| t.due(now))
+        .unwrap_or_default();
+    let steps = queues
+        .sequencer
+        .lock()
+        .map(|mut s| s.due(now))
+        .unwrap_or_default();
+    for (dest, packet) in held.into_iter().chain(steps) {
+        osc_tx.deliver(dest, &packet);
+    }
+}
+
+/// True while motors, coalesced OSC values, action steps or LEDs still have something to send
+pub(crate) fn engine_busy(queues: &EngineQueues, faders: &SharedFaders) -> bool {
+    faders.lock().is_ok_and(|f| f.motors_busy())
+        || queues.throttle.lock().is_ok_and(|t| !t.is_idle())
+        || queues.sequencer.lock().is_ok_and(|s| !s.is_idle())
+        || queues.layers.lock().is_ok_and(|l| l.leds_pending())
+}
+
 /// Recursive helper to process OSC Bundles and Messages
 #[async_recursion::async_recursion]
-async fn process_packet(
+pub(crate) async fn process_packet(
     packet: OscPacket,
-    midi_out: &mut MidiOutputConnection,
+    midi_out: &mut SurfaceOut,
     output_channel: &mut iced::futures::channel::mpsc::Sender<BridgeEvent>,
     cfg: &Arc<Config>,
-    touched: &Arc<std::sync::Mutex<[bool; 13]>>,
+    routes: &RoutingTable,
+    faders: &SharedFaders,
+    log: &Logger,
 ) {
     match packet {
         OscPacket::Message(msg) => {
-            // Listen for Eos Ping Response or any "out" message
-            if msg.addr.starts_with("/eos/out/ping") || msg.addr.starts_with("/eos/out") {
-                let _ = output_channel.send(BridgeEvent::ConnectionHeartbeat).await;
-            }
             // Handle Fader Labels
-            if msg.addr.contains("/name") {
-                let parts: Vec<&str> = msg.addr.split('/').collect();
-                if let (Some(idx_str), Some(OscType::String(name))) =
-                    (parts.get(5), msg.args.get(0))
-                {
-                    if let Ok(idx) = idx_str.parse::<u8>() {
-                        if idx >= 1 && idx <= 8 {
-                            // Send to UI
-                            let _ = output_channel
-                                .send(BridgeEvent::LabelUpdate(idx, name.clone()))
-                                .await;
-                            // Send to iCon D2 Display
-                            let words: Vec<&str> = name.split_whitespace().collect();
-                            let mcu_name = if words.len() > 2 {
-                                words[2..].join(" ")
-                            } else {
-                                name.clone()
-                            };
-                            // Remove accents
-                            let ascii_name = deunicode(&mcu_name);
-                            send_mcu_label(midi_out, idx, &ascii_name);
+            if let Some(idx) = routes.label(&msg.addr) {
+                if let Some(OscType::String(name)) = msg.args.first() {
+                    if (1..=8).contains(&idx) {
+                        // Send to UI
+                        let _ = output_channel
+                            .send(BridgeEvent::LabelUpdate(idx, name.clone()))
+                            .await;
+                        // Send to iCon D2 Display
+                        let words: Vec<&str> = name.split_whitespace().collect();
+                        let mcu_name = if words.len() > 2 {
+                            words[2..].join(" ")
+                        } else {
+                            name.clone()
+                        };
+                        // Remove accents
+                        let ascii_name = deunicode(&mcu_name);
+                        match send_label(midi_out, cfg.device_profile, idx, &ascii_name) {
+                            Ok(()) => {
+                                log.debug(format!("Sysex label fader {}: \"{}\"", idx, ascii_name))
+                            }
+                            Err(e) => log.warn(format!("Label sysex failed: {}", e)),
                         }
                     }
                 }
             }
             // Handle Motorized Fader Feedback
-            else if let Some(m) = cfg
-                .mappings
-                .iter()
-                .find(|map| msg.addr.starts_with(&map.osc_address))
-            {
-                if let Some(OscType::Float(f)) = msg.args.get(0) {
-                    let idx = m.data_number;
-                    if idx >= 1 && idx <= 8 {
-                        // Only move the motor if the user isn't physically touching it
-                        let is_touched = if let Ok(t) = touched.lock() {
-                            t[idx as usize]
-                        } else {
-                            false
+            else if let Some(route) = routes.osc_route(&msg.addr) {
+                let m = &cfg.mappings[route.mapping];
+                if let Some(OscType::Float(f)) = msg.args.first() {
+                    let idx = route.target.unwrap_or(m.data_number);
+                    if m.pickup {
+                        // Templated mappings keep one pickup per address they fill in
+                        let key = match route.target {
+                            Some(_) => msg.addr.as_str(),
+                            None => m.osc_address.as_str(),
+                        };
+                        let change = faders.lock().ok().and_then(|mut faders| {
+                            let before = faders.pickup_direction(key);
+                            let now = faders.now();
+                            faders.pickup_feedback(key, *f, cfg.pickup_tolerance, now);
+                            let after = faders.pickup_direction(key);
+                            (before != after).then_some(after)
+                        });
+                        if let Some(dir) = change {
+                            let _ = output_channel.send(BridgeEvent::Pickup(idx, dir)).await;
+                        }
+                    }
+                    // High-resolution controllers get their value back the way they sent it
+                    let ch = m.channel.unwrap_or(0) & 0x0F;
+                    let hires = match m.event_type {
+                        MidiEventType::ControlChange14 if idx < 32 => {
+                            Some(hires_cc::cc14_message(ch, idx, float_to_pitch_bend(*f)).to_vec())
+                        }
+                        MidiEventType::Nrpn => m.nrpn_parameter.map(|param| {
+                            hires_cc::nrpn_message(ch, param, float_to_pitch_bend(*f)).to_vec()
+                        }),
+                        _ => None,
+                    };
+                    if let Some(msgs) = hires {
+                        for out in msgs {
+                            if let Err(e) = midi_out.send(&out) {
+                                log.warn(format!("MIDI feedback send failed: {}", e));
+                            }
+                        }
+                    } else if (1..=8).contains(&idx) {
+                        // Only move the motor if the user isn't physically touching it,
+                        // and not back to a value that is just our own move coming back
+                        let grace = Duration::from_millis(cfg.echo_grace_ms);
+                        let (is_touched, is_echo) = match faders.lock() {
+                            Ok(mut t) => {
+                                let now = t.now();
+                                (t.is_touched(idx), t.is_echo(idx, *f, grace, now))
+                            }
+                            Err(_) => (false, false),
                         };
 
-                        if !is_touched {
-                            let pb = float_to_pitch_bend(*f);
-                            let _ = midi_out.send(&[
-                                0xE0 | (idx - 1),
-                                (pb & 0x7F) as u8,
-                                (pb >> 7) as u8,
-                            ]);
+                        if is_echo {
+                            log.debug(format!("Ignored echo {:.4} on fader {}", f, idx));
+                            let _ = output_channel.send(BridgeEvent::FaderUpdate(idx, *f)).await;
+                        } else if !is_touched {
+                            // Rate limit, deadband and smoothing decide when the motor moves
+                            let settings = m.motor.unwrap_or(cfg.motor);
+                            let pos = faders.lock().ok().and_then(|mut faders| {
+                                let now = faders.now();
+                                faders.motor_feedback(idx, *f, settings, now)
+                            });
+                            if let Some(pos) = pos {
+                                if let Err(e) = send_fader(midi_out, cfg.device_profile, idx, pos) {
+                                    log.error(format!("Motor fader send failed: {}", e));
+                                }
+                            }
                             let _ = output_channel.send(BridgeEvent::FaderUpdate(idx, *f)).await;
                         }
                     }
                 }
             }
+            // Unrouted addresses are most of the Eos subscription stream, each is logged once
+            else {
+                log.debug_once(&msg.addr, || format!("No mapping for OSC {}", msg.addr));
+            }
         }
         OscPacket::Bundle(bundle) => {
             for content in bundle.content {
-                process_packet(content, midi_out, output_channel, cfg, touched).await;
+                process_packet(content, midi_out, output_channel, cfg, routes, faders, log).await;
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::osc_transport::CapturedPackets;
+
+    fn handler(cfg: Config) -> (MidiInputHandler, CapturedPackets) {
+        let (log, _) = Logger::new();
+        let (monitor, _) = Monitor::new(Arc::new(AtomicBool::new(false)));
+        let (tx, sent) = OscSender::capture(log.clone(), monitor);
+        let (ui, _) = iced::futures::channel::mpsc::channel(16);
+        let handler = MidiInputHandler::new(Arc::new(cfg), tx, FaderState::shared(), ui, log);
+        (handler, sent)
+    }
+
+    fn addrs(sent: &CapturedPackets) -> Vec<String> {
+        let mut out = vec![];
+        for (_, p) in sent.lock().unwrap().iter() {
+            osc_transport::for_each_message(p, &mut |m| out.push(m.addr.clone()));
+        }
+        out
+    }
+
+    #[test]
+    fn a_button_fires_once_per_press() {
+        let (mut h, sent) = handler(Config::default());
+        // Play, released with a Note Off and with a velocity 0 Note On
+        h.handle(&[0x90, 94, 0x7F]);
+        h.handle(&[0x80, 94, 0x40]);
+        h.handle(&[0x90, 94, 0x7F]);
+        h.handle(&[0x90, 94, 0x00]);
+        assert_eq!(addrs(&sent), ["/eos/key/go", "/eos/key/go"]);
+    }
+
+    #[test]
+    fn cc_buttons_skip_the_throttle() {
+        let mut cfg = Config::default();
+        let play = cfg
+            .mappings
+            .iter_mut()
+            .find(|m| m.data_number == 94)
+            .unwrap();
+        play.event_type = MidiEventType::ControlChange;
+        let (mut h, sent) = handler(cfg);
+        for _ in 0..3 {
+            h.handle(&[0xB0, 94, 0x7F]);
+            h.handle(&[0xB0, 94, 0x00]);
+        }
+        assert_eq!(addrs(&sent).len(), 3);
+    }
+
+    #[test]
+    fn a_bank_press_turns_one_page() {
+        let mut cfg = Config::default();
+        // A CC bank control without a fixed value fires on press and release
+        let bank = cfg
+            .mappings
+            .iter_mut()
+            .find(|m| m.osc_address.ends_with("/page/+1"))
+            .unwrap();
+        bank.event_type = MidiEventType::ControlChange;
+        bank.fixed_osc_value = None;
+        let number = bank.data_number;
+        let (mut h, _) = handler(cfg);
+        h.handle(&[0xB0, number, 0x7F]);
+        h.handle(&[0xB0, number, 0x00]);
+        assert_eq!(h.faders.lock().unwrap().page(), 2);
+    }
+}
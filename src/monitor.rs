@@ -0,0 +1,112 @@
+//! Live traffic monitor: every MIDI and OSC message crossing the bridge, decoded for display.
+//! Entries are only built while the monitor panel is open and not paused.
+use crate::logging;
+use rosc::{OscMessage, OscType};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    MidiIn,
+    MidiOut,
+    OscIn,
+    OscOut,
+}
+
+impl std::fmt::Display for Direction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Direction::MidiIn => write!(f, "MIDI ▶"),
+            Direction::MidiOut => write!(f, "MIDI ◀"),
+            Direction::OscIn => write!(f, "OSC ◀"),
+            Direction::OscOut => write!(f, "OSC ▶"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TrafficEntry {
+    pub timestamp: String,
+    pub direction: Direction,
+    /// OSC address, or the decoded MIDI message
+    pub address: String,
+    /// OSC arguments, or the raw MIDI bytes
+    pub detail: String,
+}
+
+/// Handle shared by the midir callback, the transport and the feedback loop
+#[derive(Clone)]
+pub struct Monitor {
+    tx: mpsc::UnboundedSender<TrafficEntry>,
+    enabled: Arc<AtomicBool>,
+}
+
+impl Monitor {
+    pub fn new(enabled: Arc<AtomicBool>) -> (Self, mpsc::UnboundedReceiver<TrafficEntry>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        (Monitor { tx, enabled }, rx)
+    }
+
+    fn record(&self, direction: Direction, address: String, detail: String) {
+        let _ = self.tx.send(TrafficEntry {
+            timestamp: logging::timestamp_now(),
+            direction,
+            address,
+            detail,
+        });
+    }
+
+    pub fn osc(&self, direction: Direction, msg: &OscMessage) {
+        if self.enabled.load(Ordering::Relaxed) {
+            self.record(direction, msg.addr.clone(), format_args(&msg.args));
+        }
+    }
+
+    pub fn midi(&self, direction: Direction, bytes: &[u8]) {
+        if self.enabled.load(Ordering::Relaxed) {
+            self.record(direction, describe_midi(bytes), hex(bytes));
+        }
+    }
+}
+
+pub(crate) fn format_args(args: &[OscType]) -> String {
+    args.iter()
+        .map(|a| match a {
+            OscType::Float(f) => format!("{:.4}", f),
+            OscType::Int(i) => i.to_string(),
+            OscType::String(s) => format!("\"{}\"", s),
+            OscType::Bool(b) => b.to_string(),
+            other => format!("{:?}", other),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Human readable summary of a MIDI message
+pub fn describe_midi(bytes: &[u8]) -> String {
+    let Some(&status) = bytes.first() else {
+        return "empty".into();
+    };
+    if status == 0xF0 {
+        return format!("SysEx ({} bytes)", bytes.len());
+    }
+    let ch = (status & 0x0F) + 1;
+    let d1 = bytes.get(1).copied().unwrap_or(0);
+    let d2 = bytes.get(2).copied().unwrap_or(0);
+    match status & 0xF0 {
+        0x80 => format!("NoteOff ch{} note {}", ch, d1),
+        0x90 => format!("NoteOn ch{} note {} vel {}", ch, d1, d2),
+        0xB0 => format!("CC ch{} #{} = {}", ch, d1, d2),
+        0xE0 => format!("PitchBend ch{} {}", ch, (d2 as u16) << 7 | d1 as u16),
+        _ => format!("Status {:02X}", status),
+    }
+}
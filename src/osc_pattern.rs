@@ -0,0 +1,167 @@
+//! OSC 1.0 address patterns (`*`, `?`, `[a-z]`, `[!0]`, `{go,stop}`) plus named captures:
+//! `{n}` matches one or more characters of a segment and hands them back by name.
+//! Nothing in a pattern ever matches across a `/`.
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Literal(char),
+    /// `?`
+    AnyChar,
+    /// `*`
+    Any,
+    /// `[...]`, ranges are inclusive
+    Class {
+        negate: bool,
+        ranges: Vec<(char, char)>,
+    },
+    /// `{a,b}`
+    Alternatives(Vec<String>),
+    /// `{name}`
+    Capture(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct OscPattern {
+    tokens: Vec<Token>,
+}
+
+/// Captured values by name, in pattern order
+pub type Captures = Vec<(String, String)>;
+
+impl OscPattern {
+    /// True if `addr` uses any pattern syntax, i.e. isn't a plain address
+    pub fn is_pattern(addr: &str) -> bool {
+        addr.contains(['*', '?', '[', '{'])
+    }
+
+    pub fn parse(pattern: &str) -> Self {
+        let mut tokens = vec![];
+        let mut chars = pattern.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '?' => tokens.push(Token::AnyChar),
+                '*' => tokens.push(Token::Any),
+                '[' => {
+                    let body: String = chars.by_ref().take_while(|&c| c != ']').collect();
+                    let (negate, body) = match body.strip_prefix('!') {
+                        Some(rest) => (true, rest),
+                        None => (false, body.as_str()),
+                    };
+                    let b: Vec<char> = body.chars().collect();
+                    let mut ranges = vec![];
+                    let mut i = 0;
+                    while i < b.len() {
+                        if i + 2 < b.len() && b[i + 1] == '-' {
+                            ranges.push((b[i], b[i + 2]));
+                            i += 3;
+                        } else {
+                            ranges.push((b[i], b[i]));
+                            i += 1;
+                        }
+                    }
+                    tokens.push(Token::Class { negate, ranges });
+                }
+                '{' => {
+                    let body: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                    if body.contains(',') {
+                        tokens.push(Token::Alternatives(
+                            body.split(',').map(str::to_string).collect(),
+                        ));
+                    } else {
+                        tokens.push(Token::Capture(body));
+                    }
+                }
+                c => tokens.push(Token::Literal(c)),
+            }
+        }
+        OscPattern { tokens }
+    }
+
+    /// Named captures if `addr` matches the whole pattern
+    pub fn captures(&self, addr: &str) -> Option<Captures> {
+        let chars: Vec<char> = addr.chars().collect();
+        let mut caps = vec![];
+        match_from(&self.tokens, &chars, &mut caps).then_some(caps)
+    }
+}
+
+fn match_from(tokens: &[Token], s: &[char], caps: &mut Captures) -> bool {
+    let Some((token, rest)) = tokens.split_first() else {
+        return s.is_empty();
+    };
+    match token {
+        Token::Literal(c) => s.first() == Some(c) && match_from(rest, &s[1..], caps),
+        Token::AnyChar => s.first().is_some_and(|&c| c != '/') && match_from(rest, &s[1..], caps),
+        Token::Class { negate, ranges } => {
+            s.first().is_some_and(|&c| {
+                c != '/' && ranges.iter().any(|&(lo, hi)| (lo..=hi).contains(&c)) != *negate
+            }) && match_from(rest, &s[1..], caps)
+        }
+        Token::Alternatives(alts) => alts.iter().any(|alt| {
+            let alt: Vec<char> = alt.chars().collect();
+            s.starts_with(&alt) && match_from(rest, &s[alt.len()..], caps)
+        }),
+        Token::Any | Token::Capture(_) => {
+            let min = usize::from(matches!(token, Token::Capture(_)));
+            let segment = s.iter().position(|&c| c == '/').unwrap_or(s.len());
+            for len in min..=segment {
+                let before = caps.len();
+                if let Token::Capture(name) = token {
+                    caps.push((name.clone(), s[..len].iter().collect()));
+                }
+                if match_from(rest, &s[len..], caps) {
+                    return true;
+                }
+                caps.truncate(before);
+            }
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, addr: &str) -> bool {
+        OscPattern::parse(pattern).captures(addr).is_some()
+    }
+
+    #[test]
+    fn wildcards_stay_inside_a_segment() {
+        assert!(matches("/eos/out/fader/*/name", "/eos/out/fader/1/name"));
+        assert!(!matches("/eos/out/fader/*/name", "/eos/out/fader/1/2/name"));
+        assert!(matches("/eos/fader/1/?", "/eos/fader/1/8"));
+        assert!(!matches("/eos/fader/1/?", "/eos/fader/1/10"));
+    }
+
+    #[test]
+    fn classes_and_alternatives() {
+        assert!(matches("/eos/fader/1/[1-8]", "/eos/fader/1/5"));
+        assert!(!matches("/eos/fader/1/[1-8]", "/eos/fader/1/9"));
+        assert!(matches("/eos/fader/1/[!9]", "/eos/fader/1/3"));
+        assert!(matches("/eos/key/{go,stop}", "/eos/key/go"));
+        assert!(matches("/eos/key/{go,stop}", "/eos/key/stop"));
+        assert!(!matches("/eos/key/{go,stop}", "/eos/key/back"));
+    }
+
+    #[test]
+    fn captures_return_segment_values() {
+        let p = OscPattern::parse("/eos/out/fader/{bank}/{n}/name");
+        assert_eq!(
+            p.captures("/eos/out/fader/1/10/name"),
+            Some(vec![
+                ("bank".to_string(), "1".to_string()),
+                ("n".to_string(), "10".to_string())
+            ])
+        );
+        assert_eq!(p.captures("/eos/out/fader/1//name"), None);
+        assert_eq!(p.captures("/eos/out/fader/1/2/level"), None);
+    }
+
+    #[test]
+    fn plain_addresses_are_not_patterns() {
+        assert!(!OscPattern::is_pattern("/eos/fader/1/1"));
+        assert!(OscPattern::is_pattern("/eos/fader/1/{n}"));
+    }
+}
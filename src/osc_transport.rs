@@ -0,0 +1,489 @@
+//! OSC transport to Eos: UDP datagrams, or a TCP stream framed per OSC 1.0 (length prefix)
+//! or OSC 1.1 (SLIP). TCP connections are re-established automatically.
+//! Every configured console (primary first, then backups) is a target with a fixed index.
+//! Named non-Eos destinations are sent to over UDP.
+use crate::config::{Config, OscTransport};
+use crate::logging::Logger;
+use crate::monitor::{Direction, Monitor};
+use rosc::{decoder, encoder, OscMessage, OscPacket};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpStream, UdpSocket};
+use tokio::sync::mpsc;
+use tokio::time::{sleep, Duration};
+
+const SLIP_END: u8 = 0xC0;
+const SLIP_ESC: u8 = 0xDB;
+const SLIP_ESC_END: u8 = 0xDC;
+const SLIP_ESC_ESC: u8 = 0xDD;
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+const RECV_ERROR_DELAY: Duration = Duration::from_millis(100);
+
+/// A packet on its way out: `None` goes to the console in charge,
+/// `Some(i)` to `Config::destinations[i]`
+pub type Outgoing = (Option<usize>, OscPacket);
+
+/// Packets a capture sender would have sent, with their destination
+pub type CapturedPackets = Arc<Mutex<Vec<Outgoing>>>;
+
+/// Cheap to clone, usable from any thread or tokio task
+#[derive(Clone)]
+pub struct OscSender {
+    inner: SenderInner,
+    /// `None` when no destinations are configured
+    destinations: Option<Arc<Destinations>>,
+    active: Arc<AtomicUsize>,
+    log: Logger,
+    monitor: Monitor,
+}
+
+/// Non-Eos receivers, always over UDP whatever the console transport
+struct Destinations {
+    socket: std::net::UdpSocket,
+    addrs: Vec<String>,
+}
+
+#[derive(Clone)]
+enum SenderInner {
+    Udp {
+        socket: Arc<std::net::UdpSocket>,
+        addrs: Arc<Vec<String>>,
+    },
+    Tcp(Vec<mpsc::UnboundedSender<Vec<u8>>>),
+    /// Collects packets with their target instead of sending them, for session replay
+    Capture(CapturedPackets),
+}
+
+impl OscSender {
+    /// A sender that only records what it would send, as if to a single console
+    pub fn capture(log: Logger, monitor: Monitor) -> (Self, CapturedPackets) {
+        let sent = Arc::new(Mutex::new(vec![]));
+        (
+            OscSender {
+                inner: SenderInner::Capture(sent.clone()),
+                destinations: None,
+                active: Arc::new(AtomicUsize::new(0)),
+                log,
+                monitor,
+            },
+            sent,
+        )
+    }
+
+    /// Sends to the console currently in charge
+    pub fn send(&self, packet: &OscPacket) {
+        self.send_to(self.active.load(Ordering::Relaxed), packet);
+    }
+
+    /// Sends to the console in charge, or to one of the configured destinations
+    pub fn deliver(&self, dest: Option<usize>, packet: &OscPacket) {
+        let Some(idx) = dest else {
+            self.send(packet);
+            return;
+        };
+        let Some(buf) = self.encode(packet) else {
+            return;
+        };
+        if let SenderInner::Capture(sent) = &self.inner {
+            if let Ok(mut sent) = sent.lock() {
+                sent.push((dest, packet.clone()));
+            }
+            return;
+        }
+        let Some(dests) = &self.destinations else {
+            return;
+        };
+        if let Some(addr) = dests.addrs.get(idx) {
+            if let Err(e) = dests.socket.send_to(&buf, addr) {
+                self.log.warn(format!("OSC send to {} failed: {}", addr, e));
+            }
+        }
+    }
+
+    /// Sends to one specific console, e.g. to ping a standby backup
+    pub fn send_to(&self, target: usize, packet: &OscPacket) {
+        let Some(buf) = self.encode(packet) else {
+            return;
+        };
+        match &self.inner {
+            SenderInner::Udp { socket, addrs } => {
+                if let Some(addr) = addrs.get(target) {
+                    if let Err(e) = socket.send_to(&buf, addr) {
+                        self.log.warn(format!("OSC send to {} failed: {}", addr, e));
+                    }
+                }
+            }
+            SenderInner::Tcp(txs) => {
+                if let Some(tx) = txs.get(target) {
+                    let _ = tx.send(buf);
+                }
+            }
+            SenderInner::Capture(sent) => {
+                if let Ok(mut sent) = sent.lock() {
+                    sent.push((None, packet.clone()));
+                }
+            }
+        }
+    }
+
+    /// Wire bytes, also shown in the traffic monitor
+    fn encode(&self, packet: &OscPacket) -> Option<Vec<u8>> {
+        let buf = encoder::encode(packet).ok()?;
+        if let OscPacket::Message(msg) = packet {
+            self.monitor.osc(Direction::OscOut, msg);
+        }
+        Some(buf)
+    }
+
+    pub fn target_count(&self) -> usize {
+        match &self.inner {
+            SenderInner::Udp { addrs, .. } => addrs.len(),
+            SenderInner::Tcp(txs) => txs.len(),
+            SenderInner::Capture(_) => 1,
+        }
+    }
+}
+
+pub enum OscReceiver {
+    Udp {
+        socket: UdpSocket,
+        buf: Box<[u8; 4096]>,
+        /// IP of each target
+        hosts: Vec<String>,
+        log: Logger,
+        /// Set after a receive error so a streak of them is logged once
+        failing: bool,
+    },
+    Tcp(mpsc::UnboundedReceiver<OscInput>),
+}
+
+/// What the receiver hands to the bridge loop
+#[derive(Debug)]
+pub enum OscInput {
+    /// Packet from Eos, tagged with the target it came from when we know it
+    Packet(Option<usize>, OscPacket),
+    /// A TCP connection to this target came up, anything sent before it was dropped
+    Connected(usize),
+}
+
+impl OscReceiver {
+    /// Next packet or connection change. Cancel-safe, so it can sit in a `select!`
+    pub async fn recv(&mut self) -> Option<OscInput> {
+        match self {
+            OscReceiver::Udp {
+                socket,
+                buf,
+                hosts,
+                log,
+                failing,
+            } => loop {
+                let (len, from) = match socket.recv_from(&mut buf[..]).await {
+                    Ok(r) => r,
+                    Err(e) => {
+                        // Errors like an ICMP port unreachable repeat at once, don't spin on them
+                        if !*failing {
+                            log.warn(format!("OSC receive failed: {}", e));
+                            *failing = true;
+                        }
+                        sleep(RECV_ERROR_DELAY).await;
+                        continue;
+                    }
+                };
+                *failing = false;
+                // decode_udp is the standard for network-received OSC
+                if let Ok((_, packet)) = decoder::decode_udp(&buf[..len]) {
+                    let ip = from.ip().to_string();
+                    let from = hosts.iter().position(|h| *h == ip);
+                    return Some(OscInput::Packet(from, packet));
+                }
+            },
+            OscReceiver::Tcp(rx) => rx.recv().await,
+        }
+    }
+}
+
+/// Opens the transport selected in the config, one target per console.
+/// `active` picks the console that `OscSender::send` goes to.
+pub async fn open(
+    cfg: &Config,
+    active: Arc<AtomicUsize>,
+    log: Logger,
+    monitor: Monitor,
+) -> std::io::Result<(OscSender, OscReceiver)> {
+    let targets = cfg.eos_targets();
+    let destinations = if cfg.destinations.is_empty() {
+        None
+    } else {
+        Some(Arc::new(Destinations {
+            socket: std::net::UdpSocket::bind("0.0.0.0:0")?,
+            addrs: cfg.destinations.iter().map(|d| d.addr()).collect(),
+        }))
+    };
+    match cfg.osc_transport {
+        OscTransport::Udp => {
+            let send_socket = std::net::UdpSocket::bind("0.0.0.0:0")?;
+            let recv_socket = UdpSocket::bind(format!("0.0.0.0:{}", cfg.listen_port)).await?;
+            let hosts = targets.iter().map(|t| t.ip.clone()).collect();
+            let addrs = targets.iter().map(|t| t.addr()).collect();
+            Ok((
+                OscSender {
+                    inner: SenderInner::Udp {
+                        socket: Arc::new(send_socket),
+                        addrs: Arc::new(addrs),
+                    },
+                    destinations,
+                    active,
+                    log: log.clone(),
+                    monitor,
+                },
+                OscReceiver::Udp {
+                    socket: recv_socket,
+                    buf: Box::new([0u8; 4096]),
+                    hosts,
+                    log,
+                    failing: false,
+                },
+            ))
+        }
+        OscTransport::TcpPacketLength | OscTransport::TcpSlip => {
+            let (in_tx, in_rx) = mpsc::unbounded_channel();
+            let mut txs = vec![];
+            for (idx, target) in targets.iter().enumerate() {
+                let (out_tx, out_rx) = mpsc::unbounded_channel();
+                tokio::spawn(tcp_connection(
+                    idx,
+                    target.addr(),
+                    cfg.osc_transport,
+                    out_rx,
+                    in_tx.clone(),
+                    log.clone(),
+                ));
+                txs.push(out_tx);
+            }
+            Ok((
+                OscSender {
+                    inner: SenderInner::Tcp(txs),
+                    destinations,
+                    active,
+                    log,
+                    monitor,
+                },
+                OscReceiver::Tcp(in_rx),
+            ))
+        }
+    }
+}
+
+/// Keeps a TCP connection to Eos alive, framing outgoing packets and decoding incoming ones
+async fn tcp_connection(
+    target: usize,
+    addr: String,
+    transport: OscTransport,
+    mut outgoing: mpsc::UnboundedReceiver<Vec<u8>>,
+    incoming: mpsc::UnboundedSender<OscInput>,
+    log: Logger,
+) {
+    // Only log the first failure of a streak, we retry every couple of seconds
+    let mut failing = false;
+    loop {
+        let stream = match TcpStream::connect(&addr).await {
+            Ok(s) => s,
+            Err(e) => {
+                if !failing {
+                    log.warn(format!("TCP connection to {} failed: {}", addr, e));
+                    failing = true;
+                }
+                sleep(RECONNECT_DELAY).await;
+                continue;
+            }
+        };
+        failing = false;
+        log.info(format!("TCP connected to {}", addr));
+        let _ = stream.set_nodelay(true);
+        // Don't replay whatever piled up while we were disconnected
+        while outgoing.try_recv().is_ok() {}
+        // That includes the session setup, so have it sent again
+        if incoming.send(OscInput::Connected(target)).is_err() {
+            return;
+        }
+
+        let (mut reader, mut writer) = stream.into_split();
+        let mut rx_buf = vec![];
+        let mut chunk = [0u8; 4096];
+        loop {
+            tokio::select! {
+                out = outgoing.recv() => {
+                    // Bridge shut down
+                    let Some(payload) = out else { return };
+                    if writer.write_all(&encode_frame(transport, &payload)).await.is_err() {
+                        break;
+                    }
+                }
+                read = reader.read(&mut chunk) => {
+                    match read {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => {
+                            rx_buf.extend_from_slice(&chunk[..n]);
+                            for payload in take_frames(transport, &mut rx_buf) {
+                                if let Ok((_, packet)) = decoder::decode_udp(&payload) {
+                                    if incoming.send(OscInput::Packet(Some(target), packet)).is_err() {
+                                        return;
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        log.warn(format!("TCP connection to {} lost, reconnecting", addr));
+        sleep(RECONNECT_DELAY).await;
+    }
+}
+
+fn encode_frame(transport: OscTransport, payload: &[u8]) -> Vec<u8> {
+    match transport {
+        OscTransport::TcpSlip => {
+            // OSC 1.1 uses a "double END" so a stray byte can't corrupt the next packet
+            let mut out = vec![SLIP_END];
+            for &b in payload {
+                match b {
+                    SLIP_END => out.extend_from_slice(&[SLIP_ESC, SLIP_ESC_END]),
+                    SLIP_ESC => out.extend_from_slice(&[SLIP_ESC, SLIP_ESC_ESC]),
+                    _ => out.push(b),
+                }
+            }
+            out.push(SLIP_END);
+            out
+        }
+        _ => {
+            let mut out = (payload.len() as u32).to_be_bytes().to_vec();
+            out.extend_from_slice(payload);
+            out
+        }
+    }
+}
+
+/// Removes every complete frame from `buf`, leaving any partial frame in place
+fn take_frames(transport: OscTransport, buf: &mut Vec<u8>) -> Vec<Vec<u8>> {
+    let mut frames = vec![];
+    match transport {
+        OscTransport::TcpSlip => {
+            while let Some(end) = buf.iter().position(|&b| b == SLIP_END) {
+                let raw: Vec<u8> = buf.drain(..=end).collect();
+                let mut frame = vec![];
+                let mut escaped = false;
+                for &b in &raw[..raw.len() - 1] {
+                    match (escaped, b) {
+                        (false, SLIP_ESC) => escaped = true,
+                        (true, SLIP_ESC_END) => {
+                            frame.push(SLIP_END);
+                            escaped = false;
+                        }
+                        (true, SLIP_ESC_ESC) => {
+                            frame.push(SLIP_ESC);
+                            escaped = false;
+                        }
+                        (_, b) => {
+                            frame.push(b);
+                            escaped = false;
+                        }
+                    }
+                }
+                // Back-to-back END bytes delimit empty frames
+                if !frame.is_empty() {
+                    frames.push(frame);
+                }
+            }
+        }
+        _ => {
+            while buf.len() >= 4 {
+                let len = u32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]) as usize;
+                if buf.len() < 4 + len {
+                    break;
+                }
+                frames.push(buf[4..4 + len].to_vec());
+                buf.drain(..4 + len);
+            }
+        }
+    }
+    frames
+}
+
+/// Calls `f` on every message in a packet, descending into bundles
+pub fn for_each_message(packet: &OscPacket, f: &mut impl FnMut(&OscMessage)) {
+    match packet {
+        OscPacket::Message(m) => f(m),
+        OscPacket::Bundle(b) => {
+            for p in &b.content {
+                for_each_message(p, f);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAYLOADS: [&[u8]; 3] = [
+        b"/eos/ping\0\0\0",
+        &[SLIP_END, 1, SLIP_ESC, 2, SLIP_ESC, SLIP_ESC_END],
+        &[],
+    ];
+
+    fn round_trip(transport: OscTransport, chunk: usize) {
+        let wire: Vec<u8> = PAYLOADS
+            .iter()
+            .flat_map(|p| encode_frame(transport, p))
+            .collect();
+        let mut buf = vec![];
+        let mut frames = vec![];
+        for part in wire.chunks(chunk) {
+            buf.extend_from_slice(part);
+            frames.extend(take_frames(transport, &mut buf));
+        }
+        assert!(buf.is_empty());
+        // SLIP can't tell an empty packet from the double END between frames
+        let expected: Vec<&[u8]> = match transport {
+            OscTransport::TcpSlip => PAYLOADS[..2].to_vec(),
+            _ => PAYLOADS.to_vec(),
+        };
+        assert_eq!(frames, expected, "{:?} in {} byte reads", transport, chunk);
+    }
+
+    #[test]
+    fn frames_survive_split_and_joined_reads() {
+        for transport in [OscTransport::TcpPacketLength, OscTransport::TcpSlip] {
+            // One byte at a time, odd splits, and everything in one read
+            for chunk in [1, 3, 7, 4096] {
+                round_trip(transport, chunk);
+            }
+        }
+    }
+
+    #[test]
+    fn slip_escapes_end_and_esc() {
+        let frame = encode_frame(OscTransport::TcpSlip, &[SLIP_END, SLIP_ESC]);
+        assert_eq!(
+            frame,
+            [
+                SLIP_END,
+                SLIP_ESC,
+                SLIP_ESC_END,
+                SLIP_ESC,
+                SLIP_ESC_ESC,
+                SLIP_END
+            ]
+        );
+    }
+
+    #[test]
+    fn partial_frames_stay_buffered() {
+        let mut buf = encode_frame(OscTransport::TcpPacketLength, b"abcd");
+        buf.pop();
+        assert!(take_frames(OscTransport::TcpPacketLength, &mut buf).is_empty());
+        assert_eq!(buf.len(), 7);
+    }
+}
@@ -0,0 +1,341 @@
+//! Mapping lookup compiled once per bridge session: a hash map for MIDI input and a
+//! segment trie for Eos feedback, so neither direction scans every mapping per message.
+//! Mappings whose address is an OSC pattern or template are only tried when no plain address matches.
+use crate::config::{Config, MidiEventType};
+use crate::osc_pattern::{Captures, OscPattern};
+use crate::template;
+use std::collections::HashMap;
+
+/// Indices into `Config::mappings`; for feedback the first mapping wins when several collide
+#[derive(Debug, Default)]
+pub struct RoutingTable {
+    /// Keyed on event type and number; pitch bend numbers are the MIDI channel,
+    /// NRPN numbers the parameter. Every mapping on a key and channel fires, in config order
+    midi: HashMap<(MidiEventType, u16), Vec<MidiRoute>>,
+    /// Layer keys, indices into `Config::layers`
+    layer_keys: HashMap<(MidiEventType, u16), usize>,
+    osc: OscNode,
+    /// Pattern and template mappings, in config order
+    patterns: Vec<FeedbackPattern>,
+    /// Fader names from Eos, the `{n}` capture (or else the first) is the strip
+    labels: OscPattern,
+}
+
+/// Mapping hit by a feedback address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OscRoute {
+    pub mapping: usize,
+    /// Fader picked by the pattern's `{n}` or first capture, instead of the mapping's `data_number`
+    pub target: Option<u8>,
+}
+
+#[derive(Debug)]
+struct MidiRoute {
+    /// `None` takes every channel
+    channel: Option<u8>,
+    mapping: usize,
+}
+
+#[derive(Debug)]
+struct FeedbackPattern {
+    pattern: OscPattern,
+    mapping: usize,
+    /// The first capture is a template expression to invert, not the fader itself
+    templated: bool,
+}
+
+/// One `/`-separated address segment
+#[derive(Debug, Default)]
+struct OscNode {
+    children: HashMap<String, OscNode>,
+    mapping: Option<usize>,
+}
+
+impl RoutingTable {
+    pub fn new(cfg: &Config) -> Self {
+        let mut table = RoutingTable {
+            labels: OscPattern::parse(&cfg.label_address),
+            ..RoutingTable::default()
+        };
+        for (idx, key) in cfg.layers.iter().enumerate() {
+            table
+                .layer_keys
+                .entry((key.event_type.clone(), key.data_number as u16))
+                .or_insert(idx);
+        }
+        for (idx, m) in cfg.mappings.iter().enumerate() {
+            let templated = template::is_template(&m.osc_address);
+            // A `page` or `value` placeholder can't tell which control feedback is for
+            let receives = !templated || template::is_invertible(&m.osc_address);
+            if OscPattern::is_pattern(&m.osc_address) && receives {
+                // Placeholders read back as captures
+                table.patterns.push(FeedbackPattern {
+                    pattern: OscPattern::parse(&m.osc_address),
+                    mapping: idx,
+                    templated,
+                });
+                if !templated {
+                    // Any other pattern can't be sent as is, so it only receives
+                    continue;
+                }
+            }
+            for number in m.input_numbers().into_iter().flatten() {
+                table
+                    .midi
+                    .entry((m.event_type.clone(), number))
+                    .or_default()
+                    .push(MidiRoute {
+                        channel: m.channel,
+                        mapping: idx,
+                    });
+            }
+            if templated || m.osc_address.is_empty() {
+                continue;
+            }
+            let mut node = &mut table.osc;
+            for seg in segments(&m.osc_address) {
+                node = node.children.entry(seg.to_string()).or_default();
+            }
+            node.mapping.get_or_insert(idx);
+        }
+        table
+    }
+
+    /// Mappings listening to a decoded MIDI event on `channel` (0-15)
+    pub fn midi(
+        &self,
+        event_type: &MidiEventType,
+        number: u16,
+        channel: u8,
+    ) -> impl Iterator<Item = usize> + Clone + '_ {
+        self.midi
+            .get(&(event_type.clone(), number))
+            .map_or(&[][..], Vec::as_slice)
+            .iter()
+            .filter(move |r| r.channel.is_none_or(|ch| ch == channel))
+            .map(|r| r.mapping)
+    }
+
+    /// Layer key on a decoded MIDI event; these never fire mappings
+    pub fn layer_key(&self, event_type: &MidiEventType, number: u16) -> Option<usize> {
+        self.layer_keys.get(&(event_type.clone(), number)).copied()
+    }
+
+    /// Mapping for a feedback address: plain addresses first, then patterns in config order
+    pub fn osc_route(&self, addr: &str) -> Option<OscRoute> {
+        if let Some(mapping) = self.osc(addr) {
+            return Some(OscRoute {
+                mapping,
+                target: None,
+            });
+        }
+        self.patterns.iter().find_map(|p| {
+            let caps = p.pattern.captures(addr)?;
+            let target = if p.templated {
+                let (expr, value) = caps.first()?;
+                // Feedback for a number the template can't produce isn't ours
+                Some(u8::try_from(template::invert(expr, value)?).ok()?)
+            } else {
+                fader_number(&caps)
+            };
+            Some(OscRoute {
+                mapping: p.mapping,
+                target,
+            })
+        })
+    }
+
+    /// Strip whose name `addr` carries, per `Config::label_address`
+    pub fn label(&self, addr: &str) -> Option<u8> {
+        fader_number(&self.labels.captures(addr)?)
+    }
+
+    /// Mapping whose address equals `addr`, or is its longest prefix on a segment
+    /// boundary: `/eos/fader/1/1` covers `/eos/fader/1/1/x` but never `/eos/fader/1/10`
+    pub fn osc(&self, addr: &str) -> Option<usize> {
+        let mut node = &self.osc;
+        let mut found = node.mapping;
+        for seg in segments(addr) {
+            let Some(next) = node.children.get(seg) else {
+                break;
+            };
+            node = next;
+            found = node.mapping.or(found);
+        }
+        found
+    }
+}
+
+/// Fader from the capture named `n`, or the first capture when there is none
+fn fader_number(caps: &Captures) -> Option<u8> {
+    let (_, value) = caps.iter().find(|(name, _)| name == "n").or(caps.first())?;
+    value.parse().ok()
+}
+
+fn segments(addr: &str) -> impl Iterator<Item = &str> {
+    addr.split('/').filter(|s| !s.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::MidiOscMapping;
+
+    fn mapping(event_type: MidiEventType, data_number: u8, osc_address: &str) -> MidiOscMapping {
+        MidiOscMapping {
+            event_type,
+            data_number,
+            data_number_end: None,
+            channel: None,
+            osc_address: osc_address.into(),
+            fixed_osc_value: None,
+            osc_args: vec![],
+            nrpn_parameter: None,
+            pickup: false,
+            motor: None,
+            command: None,
+            steps: vec![],
+            destination: None,
+            layer: None,
+        }
+    }
+
+    fn midi(t: &RoutingTable, event_type: MidiEventType, number: u16) -> Vec<usize> {
+        t.midi(&event_type, number, 0).collect()
+    }
+
+    fn table(mappings: Vec<MidiOscMapping>) -> RoutingTable {
+        RoutingTable::new(&Config {
+            mappings,
+            ..Config::default()
+        })
+    }
+
+    #[test]
+    fn fader_1_does_not_match_fader_10() {
+        let t = table(vec![
+            mapping(MidiEventType::PitchBend, 1, "/eos/fader/1/1"),
+            mapping(MidiEventType::ControlChange, 10, "/eos/fader/1/10"),
+        ]);
+        assert_eq!(t.osc("/eos/fader/1/1"), Some(0));
+        assert_eq!(t.osc("/eos/fader/1/10"), Some(1));
+        assert_eq!(t.osc("/eos/fader/1/11"), None);
+    }
+
+    #[test]
+    fn shorter_address_alone_does_not_claim_longer_number() {
+        let t = table(vec![mapping(MidiEventType::PitchBend, 1, "/eos/fader/1/1")]);
+        assert_eq!(t.osc("/eos/fader/1/10"), None);
+        assert_eq!(t.osc("/eos/fader/1/1/extra"), Some(0));
+        assert_eq!(t.osc("/eos/fader/1"), None);
+    }
+
+    #[test]
+    fn longest_segment_prefix_wins() {
+        let t = table(vec![
+            mapping(MidiEventType::NoteOn, 1, "/eos/fader/1"),
+            mapping(MidiEventType::PitchBend, 2, "/eos/fader/1/2"),
+        ]);
+        assert_eq!(t.osc("/eos/fader/1/2"), Some(1));
+        assert_eq!(t.osc("/eos/fader/1/3"), Some(0));
+        assert_eq!(t.osc("/eos/fader/12"), None);
+    }
+
+    #[test]
+    fn midi_fans_out_but_feedback_takes_the_first() {
+        let t = table(vec![
+            mapping(MidiEventType::NoteOn, 94, "/eos/key/go"),
+            mapping(MidiEventType::NoteOn, 94, "/eos/key/stop"),
+            mapping(MidiEventType::NoteOn, 95, "/eos/key/go"),
+        ]);
+        assert_eq!(midi(&t, MidiEventType::NoteOn, 94), [0, 1]);
+        assert_eq!(t.osc("/eos/key/go"), Some(0));
+    }
+
+    #[test]
+    fn midi_keys_include_event_type() {
+        let mut nrpn = mapping(MidiEventType::Nrpn, 0, "/eos/wheel/level");
+        nrpn.nrpn_parameter = Some(1);
+        let t = table(vec![
+            mapping(MidiEventType::PitchBend, 1, "/eos/fader/1/1"),
+            mapping(MidiEventType::ControlChange, 1, "/eos/wheel/pan"),
+            nrpn,
+        ]);
+        assert_eq!(midi(&t, MidiEventType::PitchBend, 1), [0]);
+        assert_eq!(midi(&t, MidiEventType::ControlChange, 1), [1]);
+        assert_eq!(midi(&t, MidiEventType::Nrpn, 1), [2]);
+        assert!(midi(&t, MidiEventType::Nrpn, 0).is_empty());
+        assert!(midi(&t, MidiEventType::NoteOn, 1).is_empty());
+    }
+
+    #[test]
+    fn a_channel_only_matches_its_own_messages() {
+        let mut ch2 = mapping(MidiEventType::NoteOn, 60, "/eos/key/stop");
+        ch2.channel = Some(2);
+        let t = table(vec![mapping(MidiEventType::NoteOn, 60, "/eos/key/go"), ch2]);
+        let on = |channel| {
+            t.midi(&MidiEventType::NoteOn, 60, channel)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(on(0), [0]);
+        assert_eq!(on(2), [0, 1]);
+    }
+
+    #[test]
+    fn patterns_pick_the_target_from_their_capture() {
+        let t = table(vec![
+            mapping(MidiEventType::PitchBend, 1, "/eos/fader/1/1"),
+            mapping(MidiEventType::PitchBend, 0, "/eos/fader/1/{n}"),
+        ]);
+        let plain = t.osc_route("/eos/fader/1/1").unwrap();
+        assert_eq!((plain.mapping, plain.target), (0, None));
+        let pattern = t.osc_route("/eos/fader/1/10").unwrap();
+        assert_eq!((pattern.mapping, pattern.target), (1, Some(10)));
+        assert!(midi(&t, MidiEventType::PitchBend, 0).is_empty());
+    }
+
+    #[test]
+    fn templates_cover_their_range_both_ways() {
+        let mut pads = mapping(MidiEventType::NoteOn, 36, "/eos/chan/{note-35}");
+        pads.data_number_end = Some(51);
+        let t = table(vec![pads]);
+        assert!(midi(&t, MidiEventType::NoteOn, 35).is_empty());
+        assert_eq!(midi(&t, MidiEventType::NoteOn, 36), [0]);
+        assert_eq!(midi(&t, MidiEventType::NoteOn, 51), [0]);
+        assert!(midi(&t, MidiEventType::NoteOn, 52).is_empty());
+        let route = t.osc_route("/eos/chan/4").unwrap();
+        assert_eq!((route.mapping, route.target), (0, Some(39)));
+    }
+
+    #[test]
+    fn page_and_value_templates_only_send() {
+        let mut bank = mapping(MidiEventType::PitchBend, 1, "/eos/fader/{page}/{number}");
+        bank.data_number_end = Some(8);
+        let t = table(vec![bank]);
+        assert_eq!(midi(&t, MidiEventType::PitchBend, 3), [0]);
+        assert_eq!(t.osc_route("/eos/fader/2/3"), None);
+    }
+
+    #[test]
+    fn labels_use_the_configured_pattern() {
+        let t = table(vec![]);
+        assert_eq!(t.label("/eos/out/fader/1/3/name"), Some(3));
+        assert_eq!(t.label("/eos/out/fader/1/30/name"), Some(30));
+        assert_eq!(t.label("/eos/out/fader/1/3/level"), None);
+    }
+
+    #[test]
+    fn the_n_capture_picks_the_fader() {
+        let t = RoutingTable::new(&Config {
+            label_address: "/eos/out/fader/{bank}/{n}/name".into(),
+            mappings: vec![mapping(
+                MidiEventType::PitchBend,
+                0,
+                "/eos/fader/{bank}/{n}",
+            )],
+            ..Config::default()
+        });
+        assert_eq!(t.label("/eos/out/fader/2/7/name"), Some(7));
+        assert_eq!(t.osc_route("/eos/fader/2/7").unwrap().target, Some(7));
+    }
+}
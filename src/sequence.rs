@@ -0,0 +1,82 @@
+//! Action sequence steps waiting out their delay. They go out from the engine tick, so the
+//! MIDI handler never sleeps and a replay sends them at the same recorded times.
+use crate::osc_transport::Outgoing;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+pub type SharedSequencer = Arc<Mutex<Sequencer>>;
+
+#[derive(Debug, Default)]
+pub struct Sequencer {
+    /// Keyed on due time, then scheduling order so steps due together keep their order
+    steps: BTreeMap<(Instant, u64), Outgoing>,
+    next: u64,
+}
+
+impl Sequencer {
+    pub fn shared() -> SharedSequencer {
+        Arc::new(Mutex::new(Sequencer::default()))
+    }
+
+    pub fn schedule(&mut self, at: Instant, packet: Outgoing) {
+        self.steps.insert((at, self.next), packet);
+        self.next += 1;
+    }
+
+    /// Steps whose time has come, in order
+    pub fn due(&mut self, now: Instant) -> Vec<Outgoing> {
+        let later = self.steps.split_off(&(now, u64::MAX));
+        std::mem::replace(&mut self.steps, later)
+            .into_values()
+            .collect()
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.steps.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rosc::{OscMessage, OscPacket};
+    use std::time::Duration;
+
+    fn step(addr: &str) -> Outgoing {
+        let msg = OscMessage {
+            addr: addr.into(),
+            args: vec![],
+        };
+        (None, OscPacket::Message(msg))
+    }
+
+    fn addrs(steps: Vec<Outgoing>) -> Vec<String> {
+        steps
+            .into_iter()
+            .map(|(_, p)| match p {
+                OscPacket::Message(m) => m.addr,
+                OscPacket::Bundle(_) => unreachable!(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn interleaved_sequences_go_out_in_time_order() {
+        let t0 = Instant::now();
+        let ms = |n| t0 + Duration::from_millis(n);
+        let mut seq = Sequencer::default();
+        // Two sequences started 50 ms apart, with a step due at the same time
+        seq.schedule(ms(100), step("/a/1"));
+        seq.schedule(ms(300), step("/a/2"));
+        seq.schedule(ms(150), step("/b/1"));
+        seq.schedule(ms(300), step("/b/2"));
+
+        assert!(seq.due(ms(99)).is_empty());
+        assert_eq!(addrs(seq.due(ms(100))), ["/a/1"]);
+        assert_eq!(addrs(seq.due(ms(299))), ["/b/1"]);
+        assert!(!seq.is_idle());
+        assert_eq!(addrs(seq.due(ms(300))), ["/a/2", "/b/2"]);
+        assert!(seq.is_idle());
+    }
+}
@@ -0,0 +1,278 @@
+//! Session capture and replay: every MIDI message from the surface and every OSC packet
+//! from Eos is written as a JSON line with its offset from the start of the recording.
+//! Replaying feeds the same input through the engine against capture sinks, so a
+//! rehearsal glitch can be reproduced off-site without a console or a surface.
+use crate::config::Config;
+use crate::faders::{FaderState, SharedFaders};
+use crate::logging::{self, Logger};
+use crate::midi_osc_logic::{
+    engine_busy, engine_tick, process_packet, BridgeEvent, MidiInputHandler, SurfaceOut,
+    ENGINE_TICK,
+};
+use crate::monitor::{self, Monitor};
+use crate::osc_transport::{CapturedPackets, OscSender};
+use rosc::{decoder, encoder, OscPacket};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SessionEvent {
+    MidiIn {
+        t_ms: u64,
+        bytes: Vec<u8>,
+    },
+    /// Raw OSC packet as received from the console in charge
+    OscIn {
+        t_ms: u64,
+        bytes: Vec<u8>,
+    },
+}
+
+impl SessionEvent {
+    fn t_ms(&self) -> u64 {
+        match self {
+            SessionEvent::MidiIn { t_ms, .. } | SessionEvent::OscIn { t_ms, .. } => *t_ms,
+        }
+    }
+}
+
+struct Recording {
+    file: BufWriter<File>,
+    started: Instant,
+}
+
+/// Shared between the GUI, which starts and stops it, and the bridge, which feeds it
+#[derive(Clone, Default)]
+pub struct SessionRecorder {
+    inner: Arc<Mutex<Option<Recording>>>,
+}
+
+impl SessionRecorder {
+    /// Starts a new recording in the `sessions` folder next to the config
+    pub fn start(&self) -> std::io::Result<PathBuf> {
+        let dir = sessions_dir()?;
+        fs::create_dir_all(&dir)?;
+        let stamp = logging::timestamp_now().replace([' ', ':'], "-");
+        let path = dir.join(format!("session-{}.jsonl", stamp));
+        let file = BufWriter::new(File::create(&path)?);
+        if let Ok(mut inner) = self.inner.lock() {
+            *inner = Some(Recording {
+                file,
+                started: Instant::now(),
+            });
+        }
+        Ok(path)
+    }
+
+    pub fn stop(&self) {
+        if let Ok(mut inner) = self.inner.lock() {
+            if let Some(mut rec) = inner.take() {
+                let _ = rec.file.flush();
+            }
+        }
+    }
+
+    /// MIDI message as it arrived `at`, which may be a while before it gets here
+    pub fn midi_in(&self, bytes: &[u8], at: Instant) {
+        self.write(at, |t_ms| SessionEvent::MidiIn {
+            t_ms,
+            bytes: bytes.to_vec(),
+        });
+    }
+
+    pub fn osc_in(&self, packet: &OscPacket) {
+        if let Ok(bytes) = encoder::encode(packet) {
+            self.write(Instant::now(), |t_ms| SessionEvent::OscIn { t_ms, bytes });
+        }
+    }
+
+    fn write(&self, at: Instant, event: impl FnOnce(u64) -> SessionEvent) {
+        let Ok(mut inner) = self.inner.lock() else {
+            return;
+        };
+        let Some(rec) = inner.as_mut() else {
+            return;
+        };
+        let event = event(at.saturating_duration_since(rec.started).as_millis() as u64);
+        if let Ok(line) = serde_json::to_string(&event) {
+            let _ = writeln!(rec.file, "{}", line);
+        }
+    }
+}
+
+fn sessions_dir() -> std::io::Result<PathBuf> {
+    let cfg_path = confy::get_configuration_file_path("eos-midi-bridge", None)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::NotFound, e))?;
+    Ok(cfg_path
+        .parent()
+        .map(|p| p.join("sessions"))
+        .unwrap_or_else(|| PathBuf::from("sessions")))
+}
+
+#[derive(Debug, Clone)]
+pub struct ReplaySummary {
+    pub events: usize,
+    pub midi_out: usize,
+    pub osc_out: usize,
+    /// Everything the engine produced, one line per output, next to the session file
+    pub report: PathBuf,
+}
+
+pub fn load_session(path: &Path) -> anyhow::Result<Vec<SessionEvent>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut events = vec![];
+    for (n, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        events.push(
+            serde_json::from_str(&line).map_err(|e| anyhow::anyhow!("line {}: {}", n + 1, e))?,
+        );
+    }
+    Ok(events)
+}
+
+/// Runs a recorded session through the engine as fast as possible, in recorded order.
+/// The output only depends on the session file and `cfg`, so two replays can be diffed.
+pub async fn replay_session(path: PathBuf, cfg: Arc<Config>) -> anyhow::Result<ReplaySummary> {
+    let events = load_session(&path)?;
+
+    let (log, _log_rx) = Logger::new();
+    let (monitor, _monitor_rx) = Monitor::new(Arc::new(AtomicBool::new(false)));
+    let (osc_tx, osc_sent) = OscSender::capture(log.clone(), monitor.clone());
+    let faders = FaderState::shared();
+    let (mut ui_tx, mut ui_rx) = iced::futures::channel::mpsc::channel(1024);
+    let mut handler = MidiInputHandler::new(
+        cfg.clone(),
+        osc_tx.clone(),
+        faders.clone(),
+        ui_tx.clone(),
+        log.clone(),
+    );
+    let queues = handler.queues();
+    let routes = handler.routes();
+    let mut surface = SurfaceOut::capture(monitor);
+
+    let mut lines = vec![];
+    let (mut midi_out, mut osc_out) = (0, 0);
+    // Time-dependent logic runs on recorded time, so a replay doesn't depend on host speed
+    let start = Instant::now();
+    let mut clock = Duration::ZERO;
+    // One extra round after the last event lets the motors settle
+    for event in events.iter().map(Some).chain([None]) {
+        let until = event.map_or(Duration::MAX, |e| Duration::from_millis(e.t_ms()));
+        // Output held back since the previous event, on the same tick as live
+        while clock + ENGINE_TICK <= until && engine_busy(&queues, &faders) {
+            clock += ENGINE_TICK;
+            set_clock(&faders, start + clock);
+            engine_tick(&mut surface, &osc_tx, &queues, &cfg, &faders, &log);
+            let at = clock.as_millis();
+            osc_out += drain_osc(&osc_sent, &cfg, at, &mut lines);
+            for bytes in surface.take_captured() {
+                lines.push(format!("{:>9} MIDI out {}", at, describe(&bytes)));
+                midi_out += 1;
+            }
+        }
+        let Some(event) = event else {
+            break;
+        };
+        let t = event.t_ms();
+        clock = Duration::from_millis(t);
+        set_clock(&faders, start + clock);
+        match event {
+            SessionEvent::MidiIn { bytes, .. } => {
+                lines.push(format!("{:>9} MIDI in  {}", t, describe(bytes)));
+                handler.handle(bytes);
+            }
+            SessionEvent::OscIn { bytes, .. } => {
+                let Ok((_, packet)) = decoder::decode_udp(bytes) else {
+                    lines.push(format!("{:>9} OSC in   <undecodable>", t));
+                    continue;
+                };
+                lines.push(format!("{:>9} OSC in   {}", t, describe_osc(&packet)));
+                process_packet(
+                    packet,
+                    &mut surface,
+                    &mut ui_tx,
+                    &cfg,
+                    &routes,
+                    &faders,
+                    &log,
+                )
+                .await;
+            }
+        }
+
+        osc_out += drain_osc(&osc_sent, &cfg, t as u128, &mut lines);
+        for bytes in surface.take_captured() {
+            lines.push(format!("{:>9} MIDI out {}", t, describe(&bytes)));
+            midi_out += 1;
+        }
+        while let Ok(ui) = ui_rx.try_recv() {
+            match ui {
+                BridgeEvent::FaderUpdate(idx, v) => {
+                    lines.push(format!("{:>9} UI       fader {} = {:.4}", t, idx, v))
+                }
+                BridgeEvent::LabelUpdate(idx, label) => {
+                    lines.push(format!("{:>9} UI       label {} = {:?}", t, idx, label))
+                }
+                BridgeEvent::Pickup(idx, dir) => {
+                    lines.push(format!("{:>9} UI       pickup {} = {:?}", t, idx, dir))
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let report = path.with_extension("replay.txt");
+    fs::write(&report, lines.join("\n") + "\n")?;
+    Ok(ReplaySummary {
+        events: events.len(),
+        midi_out,
+        osc_out,
+        report,
+    })
+}
+
+fn set_clock(faders: &SharedFaders, now: Instant) {
+    if let Ok(mut faders) = faders.lock() {
+        faders.set_replay_clock(now);
+    }
+}
+
+fn drain_osc(sent: &CapturedPackets, cfg: &Config, at: u128, lines: &mut Vec<String>) -> usize {
+    let Ok(mut sent) = sent.lock() else {
+        return 0;
+    };
+    for (dest, packet) in sent.iter() {
+        let to = match dest.and_then(|i| cfg.destinations.get(i)) {
+            Some(d) => format!("[{}] ", d.name),
+            None => String::new(),
+        };
+        lines.push(format!("{:>9} OSC out  {}{}", at, to, describe_osc(packet)));
+    }
+    sent.drain(..).count()
+}
+
+fn describe(bytes: &[u8]) -> String {
+    format!(
+        "{} [{}]",
+        monitor::describe_midi(bytes),
+        monitor::hex(bytes)
+    )
+}
+
+fn describe_osc(packet: &OscPacket) -> String {
+    let mut parts = vec![];
+    crate::osc_transport::for_each_message(packet, &mut |m| {
+        parts.push(format!("{} {}", m.addr, monitor::format_args(&m.args)));
+    });
+    parts.join(" | ")
+}
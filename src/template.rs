@@ -0,0 +1,292 @@
+//! Outgoing address and argument templates filled in from the MIDI event:
+//! `/eos/fader/1/{channel+1}`, `/eos/chan/{note-35}`, `/eos/fader/{page}/...`.
+//! A placeholder is a variable with an optional `*n` scale and `+n` / `-n` offset.
+use crate::config::{ActionStep, EosCommand, OscArg, OscArgType};
+use rosc::{OscMessage, OscType};
+
+/// What a template can refer to for one MIDI event
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TemplateVars {
+    /// MIDI channel of the message, 0-15
+    pub channel: u8,
+    /// Decoded number: fader for pitch bend and HUI faders, note, CC, HUI switch or NRPN parameter
+    pub number: u16,
+    /// Normalized value, 0.0-1.0
+    pub value: f32,
+    /// Fader page the bridge last moved Eos to
+    pub page: u16,
+}
+
+const VARIABLES: [&str; 5] = ["channel", "number", "note", "value", "page"];
+
+/// True if `s` has placeholders and all of them are template expressions
+pub fn is_template(s: &str) -> bool {
+    let mut any = false;
+    for expr in placeholders(s) {
+        if parse(expr).is_none() {
+            return false;
+        }
+        any = true;
+    }
+    any && !s.contains(['*', '?', '['])
+}
+
+/// Fills in every placeholder; unknown ones are left as written
+pub fn render(s: &str, vars: &TemplateVars) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find('{') {
+        let Some(len) = rest[start..].find('}') else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        let expr = &rest[start + 1..start + len];
+        match parse(expr) {
+            Some(expr) => out.push_str(&eval(expr, vars)),
+            None => out.push_str(&rest[start..=start + len]),
+        }
+        rest = &rest[start + len + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Outgoing arguments for one event. A typed template that doesn't convert fails the
+/// whole list, so later arguments never shift into the wrong position
+pub fn render_args(args: &[OscArg], vars: &TemplateVars) -> Result<Vec<OscType>, String> {
+    args.iter()
+        .map(|arg| match arg {
+            OscArg::Bool(b) => Ok(OscType::Bool(*b)),
+            OscArg::Int(i) => Ok(OscType::Int(*i)),
+            OscArg::Float(f) => Ok(OscType::Float(*f)),
+            OscArg::Template(t) => {
+                let t = render(t, vars);
+                Ok(match t.parse() {
+                    Ok(f) => OscType::Float(f),
+                    Err(_) => OscType::String(t),
+                })
+            }
+            OscArg::Typed { kind, value } => {
+                let text = render(value, vars);
+                convert(*kind, text.clone())
+                    .ok_or_else(|| format!("\"{}\" is not a valid {:?}", text, kind))
+            }
+        })
+        .collect()
+}
+
+/// Command line message for one event
+pub fn render_command(cmd: &EosCommand, vars: &TemplateVars) -> OscMessage {
+    let mut text = render(&cmd.text, vars);
+    if cmd.enter && !text.trim_end().ends_with('#') {
+        text.push('#');
+    }
+    OscMessage {
+        addr: cmd.address().to_string(),
+        args: vec![OscType::String(text)],
+    }
+}
+
+/// Message for one step of an action sequence
+pub fn render_step(step: &ActionStep, vars: &TemplateVars) -> Result<OscMessage, String> {
+    Ok(match &step.command {
+        Some(cmd) => render_command(cmd, vars),
+        None => OscMessage {
+            addr: render(&step.osc_address, vars),
+            args: render_args(&step.osc_args, vars)?,
+        },
+    })
+}
+
+fn convert(kind: OscArgType, s: String) -> Option<OscType> {
+    let number = || s.trim().parse::<f64>().ok();
+    Some(match kind {
+        OscArgType::String => OscType::String(s),
+        OscArgType::Float => OscType::Float(number()? as f32),
+        OscArgType::Int => OscType::Int(number()?.round() as i32),
+        OscArgType::Bool => OscType::Bool(match s.trim() {
+            "true" => true,
+            "false" => false,
+            _ => number()? != 0.0,
+        }),
+    })
+}
+
+/// True if feedback can tell the control from the first placeholder, i.e. it is a
+/// `number`, `note` or `channel` expression. `page` and `value` templates only send
+pub fn is_invertible(s: &str) -> bool {
+    placeholders(s)
+        .next()
+        .and_then(parse)
+        .is_some_and(|e| matches!(e.var, "number" | "note" | "channel"))
+}
+
+/// Number a placeholder read back from feedback stands for: `{note-35}` read as `4` is
+/// note 39. A `channel` gives its pitch bend fader number, channel + 1
+pub fn invert(expr: &str, captured: &str) -> Option<u16> {
+    let e = parse(expr)?;
+    if e.scale == 0.0 {
+        return None;
+    }
+    let n = (captured.trim().parse::<f64>().ok()? - e.offset) / e.scale;
+    let n = match e.var {
+        "number" | "note" => n,
+        "channel" => n + 1.0,
+        _ => return None,
+    };
+    // Anything that doesn't land on a whole number wasn't one of ours
+    if n < 0.0 || (n - n.round()).abs() > 1e-6 {
+        return None;
+    }
+    u16::try_from(n.round() as i64).ok()
+}
+
+fn placeholders(s: &str) -> impl Iterator<Item = &str> {
+    s.split('{')
+        .skip(1)
+        .filter_map(|part| part.split_once('}').map(|(expr, _)| expr))
+}
+
+/// `var`, `var+3`, `var-35` or `value*100`
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Expr<'a> {
+    var: &'a str,
+    scale: f64,
+    offset: f64,
+}
+
+fn parse(expr: &str) -> Option<Expr<'_>> {
+    let expr = expr.trim();
+    let split = expr.find(['*', '+', '-']).unwrap_or(expr.len());
+    let (var, mut rest) = expr.split_at(split);
+    let var = var.trim();
+    if !VARIABLES.contains(&var) {
+        return None;
+    }
+    let mut scale = 1.0;
+    if let Some(r) = rest.strip_prefix('*') {
+        let end = r.find(['+', '-']).unwrap_or(r.len());
+        scale = r[..end].trim().parse().ok()?;
+        rest = &r[end..];
+    }
+    let offset = match rest.trim() {
+        "" => 0.0,
+        o => {
+            let n: f64 = o[1..].trim().parse().ok()?;
+            if o.starts_with('-') {
+                -n
+            } else {
+                n
+            }
+        }
+    };
+    Some(Expr { var, scale, offset })
+}
+
+fn eval(expr: Expr, vars: &TemplateVars) -> String {
+    let base = match expr.var {
+        "channel" => vars.channel as f64,
+        "number" | "note" => vars.number as f64,
+        "page" => vars.page as f64,
+        _ => return format!("{}", vars.value as f64 * expr.scale + expr.offset),
+    };
+    format!("{}", (base * expr.scale + expr.offset).round() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VARS: TemplateVars = TemplateVars {
+        channel: 2,
+        number: 40,
+        value: 0.5,
+        page: 3,
+    };
+
+    #[test]
+    fn renders_offsets() {
+        assert_eq!(render("/eos/fader/1/{channel+1}", &VARS), "/eos/fader/1/3");
+        assert_eq!(render("/eos/chan/{note-35}", &VARS), "/eos/chan/5");
+        assert_eq!(
+            render("/eos/fader/{page}/{number}", &VARS),
+            "/eos/fader/3/40"
+        );
+        assert_eq!(render("{value}", &VARS), "0.5");
+        assert_eq!(render("{value*100}", &VARS), "50");
+        assert_eq!(render("{note*2+1}", &VARS), "81");
+    }
+
+    #[test]
+    fn feedback_inverts_the_expression() {
+        assert_eq!(invert("note-35", "4"), Some(39));
+        assert_eq!(invert("number", "7"), Some(7));
+        assert_eq!(invert("channel+1", "3"), Some(3));
+        assert_eq!(invert("note*2+1", "81"), Some(40));
+        assert_eq!(invert("note*2", "81"), None);
+        assert_eq!(invert("page", "3"), None);
+        assert_eq!(invert("value*100", "50"), None);
+        assert!(is_invertible("/eos/chan/{note-35}"));
+        assert!(!is_invertible("/eos/fader/{page}/{number}"));
+    }
+
+    #[test]
+    fn commands_render_and_enter() {
+        let cmd = EosCommand {
+            text: "Chan {note-35} Full".into(),
+            enter: true,
+            append: false,
+        };
+        let msg = render_command(&cmd, &VARS);
+        assert_eq!(msg.addr, "/eos/newcmd");
+        assert_eq!(msg.args, vec![OscType::String("Chan 5 Full#".into())]);
+    }
+
+    #[test]
+    fn typed_args_convert_after_rendering() {
+        let typed = |kind, value: &str| OscArg::Typed {
+            kind,
+            value: value.into(),
+        };
+        let args = [
+            OscArg::Int(1),
+            OscArg::Template("{value}".into()),
+            OscArg::Template("Chan {note-35}".into()),
+            typed(OscArgType::Int, "{value*100}"),
+            typed(OscArgType::String, "{note}"),
+            typed(OscArgType::Bool, "{value}"),
+        ];
+        assert_eq!(
+            render_args(&args, &VARS),
+            Ok(vec![
+                OscType::Int(1),
+                OscType::Float(0.5),
+                OscType::String("Chan 5".into()),
+                OscType::Int(50),
+                OscType::String("40".into()),
+                OscType::Bool(true),
+            ])
+        );
+    }
+
+    #[test]
+    fn a_bad_typed_arg_fails_the_whole_list() {
+        let args = [
+            OscArg::Typed {
+                kind: OscArgType::Float,
+                value: "not a number".into(),
+            },
+            OscArg::Int(1),
+        ];
+        assert!(render_args(&args, &VARS).is_err());
+    }
+
+    #[test]
+    fn unknown_placeholders_stay() {
+        assert_eq!(render("/eos/fader/1/{n}", &VARS), "/eos/fader/1/{n}");
+        assert!(!is_template("/eos/fader/1/{n}"));
+        assert!(!is_template("/eos/fader/1/1"));
+        assert!(!is_template("/eos/*/{channel}"));
+        assert!(is_template("/eos/fader/1/{channel + 1}"));
+    }
+}
@@ -0,0 +1,130 @@
+//! Coalesces continuous OSC values per address so fast fader moves don't flood the
+//! lighting network. The latest value always goes out, at most `max_rate_hz` times a second.
+use crate::osc_transport::Outgoing;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+pub type SharedThrottle = Arc<Mutex<OscThrottle>>;
+
+#[derive(Debug, Default)]
+pub struct OscThrottle {
+    /// `None` sends everything straight away
+    interval: Option<Duration>,
+    /// Keyed on destination and address, ordered so flushes come out the same way in a replay
+    addrs: BTreeMap<(Option<usize>, String), Slot>,
+}
+
+#[derive(Debug, Default)]
+struct Slot {
+    last_sent: Option<Instant>,
+    pending: Option<Outgoing>,
+}
+
+impl OscThrottle {
+    pub fn shared(max_rate_hz: f32) -> SharedThrottle {
+        Arc::new(Mutex::new(OscThrottle {
+            interval: (max_rate_hz > 0.0).then(|| Duration::from_secs_f32(1.0 / max_rate_hz)),
+            addrs: BTreeMap::new(),
+        }))
+    }
+
+    /// Returns the packet if it may go out now, otherwise holds it in place of any older value
+    pub fn offer(&mut self, addr: &str, packet: Outgoing, now: Instant) -> Option<Outgoing> {
+        let Some(interval) = self.interval else {
+            return Some(packet);
+        };
+        let slot = self.addrs.entry((packet.0, addr.to_string())).or_default();
+        if slot
+            .last_sent
+            .is_some_and(|t| now.duration_since(t) < interval)
+        {
+            slot.pending = Some(packet);
+            return None;
+        }
+        slot.pending = None;
+        slot.last_sent = Some(now);
+        Some(packet)
+    }
+
+    /// Held values whose address may send again
+    pub fn due(&mut self, now: Instant) -> Vec<Outgoing> {
+        let Some(interval) = self.interval else {
+            return vec![];
+        };
+        let mut due = vec![];
+        for slot in self.addrs.values_mut() {
+            if slot.pending.is_some()
+                && slot
+                    .last_sent
+                    .is_none_or(|t| now.duration_since(t) >= interval)
+            {
+                due.extend(slot.pending.take());
+                slot.last_sent = Some(now);
+            }
+        }
+        due
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.addrs.values().all(|s| s.pending.is_none())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rosc::{OscMessage, OscPacket, OscType};
+
+    fn level(dest: Option<usize>, v: f32) -> Outgoing {
+        let msg = OscMessage {
+            addr: "/eos/fader/1/1".into(),
+            args: vec![OscType::Float(v)],
+        };
+        (dest, OscPacket::Message(msg))
+    }
+
+    fn ms(t0: Instant, n: u64) -> Instant {
+        t0 + Duration::from_millis(n)
+    }
+
+    #[test]
+    fn coalesces_and_flushes_the_latest_value() {
+        let t0 = Instant::now();
+        let throttle = OscThrottle::shared(10.0);
+        let mut t = throttle.lock().unwrap();
+        assert_eq!(t.offer("/a", level(None, 0.1), t0), Some(level(None, 0.1)));
+        assert_eq!(t.offer("/a", level(None, 0.2), ms(t0, 20)), None);
+        assert_eq!(t.offer("/a", level(None, 0.3), ms(t0, 40)), None);
+        assert!(t.due(ms(t0, 50)).is_empty());
+        assert_eq!(t.due(ms(t0, 101)), [level(None, 0.3)]);
+        assert!(t.is_idle());
+        // The flush counts as a send
+        assert_eq!(t.offer("/a", level(None, 0.4), ms(t0, 150)), None);
+    }
+
+    #[test]
+    fn zero_rate_sends_everything() {
+        let t0 = Instant::now();
+        let throttle = OscThrottle::shared(0.0);
+        let mut t = throttle.lock().unwrap();
+        for v in [0.1, 0.2, 0.3] {
+            assert_eq!(t.offer("/a", level(None, v), t0), Some(level(None, v)));
+        }
+        assert!(t.due(t0).is_empty());
+        assert!(t.is_idle());
+    }
+
+    #[test]
+    fn addresses_and_destinations_throttle_apart() {
+        let t0 = Instant::now();
+        let throttle = OscThrottle::shared(10.0);
+        let mut t = throttle.lock().unwrap();
+        assert!(t.offer("/a", level(None, 0.1), t0).is_some());
+        assert!(t.offer("/b", level(None, 0.1), t0).is_some());
+        assert!(t.offer("/a", level(Some(0), 0.1), t0).is_some());
+        assert!(t.offer("/a", level(Some(0), 0.2), ms(t0, 10)).is_none());
+        assert!(t.offer("/a", level(None, 0.2), ms(t0, 10)).is_none());
+        assert_eq!(t.due(ms(t0, 101)), [level(None, 0.2), level(Some(0), 0.2)]);
+    }
+}